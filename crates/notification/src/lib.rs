@@ -2,7 +2,7 @@ use std::path::Path;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Error)]
 pub enum NotificationError {
@@ -15,21 +15,86 @@ pub enum NotificationError {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct DetectionEvent {
     pub timestamp: DateTime<Utc>,
     pub plate_number: String,
     pub confidence: f32,
     pub image_path: String,
     pub access_status: AccessStatus,
+    /// Whether the plate text could be read reliably
+    #[serde(default)]
+    pub read_status: ReadStatus,
+    /// Vehicle category from the plate colour
+    #[serde(default)]
+    pub plate_type: PlateType,
+    /// Confidence of each character of `plate_number`, when the OCR engine reports it
+    #[serde(default)]
+    pub char_confidences: Vec<f32>,
+    /// Why `access_status` was chosen
+    #[serde(default)]
+    pub reason: String,
+    /// Id of the access policy rule that produced `access_status`
+    #[serde(default)]
+    pub rule_id: Option<String>,
+    /// How `plate_number` was matched to the vehicle registry, if it was
+    #[serde(default)]
+    pub match_kind: Option<MatchKind>,
+    /// Weighted edit distance between the read and the registered plate
+    #[serde(default)]
+    pub match_distance: Option<f32>,
+    /// Whether the plate, or one of its alternative readings, is on the watchlist
+    #[serde(default)]
+    pub watchlisted: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Characters read with less confidence than this are flagged in messages.
+const UNCERTAIN_CHAR_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum AccessStatus {
     Allowed,
     Denied,
     Suspicious,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PlateType {
+    Private,
+    Taxi,
+    Commercial,
+    Diplomat,
+    Temporary,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// The read equals the registered plate
+    Exact,
+    /// One of the OCR's alternative readings equals the registered plate
+    Alternative,
+    /// The read is within the allowed edit distance of the registered plate
+    Fuzzy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ReadStatus {
+    #[default]
+    Valid,
+    InvalidFormat,
+    Empty,
+    LowConfidence,
+}
+
 pub struct NotificationService {
     line_token: Option<String>,
     telegram_token: Option<String>,
@@ -50,14 +115,36 @@ impl NotificationService {
     }
 
     pub async fn send_alert(&self, event: &DetectionEvent) -> Result<(), NotificationError> {
-        let message = self.format_message(event);
-        let image_path = Path::new(&event.image_path);
-
         info!("Sending alert for plate: {}", event.plate_number);
+        self.send_to_all(&self.format_message(event), Path::new(&event.image_path))
+            .await
+    }
 
+    /// Sends the alert an event calls for: watchlist hits on the priority
+    /// path, any other denied or suspicious read as a routine alert, and
+    /// nothing for allowed ones.
+    pub async fn notify(&self, event: &DetectionEvent) -> Result<(), NotificationError> {
+        if event.watchlisted {
+            return self.send_priority_alert(event).await;
+        }
+        match event.access_status {
+            AccessStatus::Denied | AccessStatus::Suspicious => self.send_alert(event).await,
+            AccessStatus::Allowed => Ok(()),
+        }
+    }
+
+    /// Sends a watchlist hit with the priority template, which leads with the
+    /// reason the plate is denied so it stands out from routine alerts.
+    pub async fn send_priority_alert(&self, event: &DetectionEvent) -> Result<(), NotificationError> {
+        warn!("Sending PRIORITY alert for plate: {} ({})", event.plate_number, event.reason);
+        self.send_to_all(&self.format_priority_message(event), Path::new(&event.image_path))
+            .await
+    }
+
+    async fn send_to_all(&self, message: &str, image_path: &Path) -> Result<(), NotificationError> {
         // Try sending through LINE Notify
         if let Some(token) = &self.line_token {
-            match self.send_line_notify(&message, Some(image_path)).await {
+            match self.send_line_notify(message, Some(image_path)).await {
                 Ok(_) => debug!("Successfully sent LINE notification"),
                 Err(e) => error!("Failed to send LINE notification: {}", e),
             }
@@ -65,7 +152,7 @@ impl NotificationService {
 
         // Try sending through Telegram
         if let (Some(token), Some(chat_id)) = (&self.telegram_token, &self.telegram_chat_id) {
-            match self.send_telegram(&message, Some(image_path)).await {
+            match self.send_telegram(message, Some(image_path)).await {
                 Ok(_) => debug!("Successfully sent Telegram message"),
                 Err(e) => error!("Failed to send Telegram message: {}", e),
             }
@@ -81,16 +168,110 @@ impl NotificationService {
             AccessStatus::Suspicious => "⚠️ Suspicious",
         };
 
-        format!(
+        let plate = if event.plate_number.is_empty() {
+            "(unreadable)"
+        } else {
+            event.plate_number.as_str()
+        };
+
+        let mut message = format!(
             "🚗 License Plate Detection\n\n\
              Plate: {}\n\
              Status: {}\n\
              Confidence: {:.1}%\n\
              Time: {}",
-            event.plate_number,
+            plate,
             status,
             event.confidence * 100.0,
             event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        );
+
+        let plate_type = match event.plate_type {
+            PlateType::Private => Some("Private"),
+            PlateType::Taxi => Some("Taxi"),
+            PlateType::Commercial => Some("Commercial"),
+            PlateType::Diplomat => Some("Diplomat"),
+            PlateType::Temporary => Some("Temporary"),
+            PlateType::Unknown => None,
+        };
+        if let Some(plate_type) = plate_type {
+            message.push_str(&format!("\nType: {}", plate_type));
+        }
+
+        let read_note = match event.read_status {
+            ReadStatus::Valid => None,
+            ReadStatus::InvalidFormat => Some("Read: Invalid plate format"),
+            ReadStatus::Empty => Some("Read: No text recognized"),
+            ReadStatus::LowConfidence => Some("Read: Low confidence"),
+        };
+        if let Some(note) = read_note {
+            message.push('\n');
+            message.push_str(note);
+        }
+
+        if let Some(marked) = self.mark_uncertain_chars(event) {
+            message.push_str(&format!("\nUncertain: {}", marked));
+        }
+
+        if !event.reason.is_empty() {
+            message.push_str(&format!("\nReason: {}", event.reason));
+            if let Some(rule_id) = &event.rule_id {
+                message.push_str(&format!(" [{}]", rule_id));
+            }
+        }
+
+        match (event.match_kind, event.match_distance) {
+            (Some(MatchKind::Fuzzy), Some(distance)) => {
+                message.push_str(&format!("\nMatch: fuzzy (distance {:.2})", distance))
+            }
+            (Some(MatchKind::Alternative), _) => message.push_str("\nMatch: alternative reading"),
+            _ => {}
+        }
+
+        message
+    }
+
+    fn format_priority_message(&self, event: &DetectionEvent) -> String {
+        let mut message = format!(
+            "🚨 WATCHLIST ALERT 🚨\n\n\
+             Plate: {}\n\
+             Reason: {}\n\
+             Status: ❌ Denied\n\
+             Confidence: {:.1}%\n\
+             Time: {}",
+            event.plate_number,
+            if event.reason.is_empty() { "on watchlist" } else { event.reason.as_str() },
+            event.confidence * 100.0,
+            event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        );
+
+        if let Some(marked) = self.mark_uncertain_chars(event) {
+            message.push_str(&format!("\nUncertain: {}", marked));
+        }
+
+        message.push_str("\n\nDo not open the gate. Notify security immediately.");
+        message
+    }
+
+    /// Replaces low-confidence characters of the plate with `?`, or returns
+    /// `None` when every character is confident or no per-character data exists.
+    fn mark_uncertain_chars(&self, event: &DetectionEvent) -> Option<String> {
+        if event.char_confidences.len() != event.plate_number.chars().count()
+            || event
+                .char_confidences
+                .iter()
+                .all(|c| *c >= UNCERTAIN_CHAR_THRESHOLD)
+        {
+            return None;
+        }
+
+        Some(
+            event
+                .plate_number
+                .chars()
+                .zip(&event.char_confidences)
+                .map(|(ch, c)| if *c < UNCERTAIN_CHAR_THRESHOLD { '?' } else { ch })
+                .collect(),
         )
     }
 
@@ -241,12 +422,96 @@ mod tests {
             confidence: 0.95,
             image_path: "test.jpg".into(),
             access_status: AccessStatus::Suspicious,
+            read_status: ReadStatus::Valid,
+            plate_type: PlateType::Taxi,
+            char_confidences: Vec::new(),
+            reason: String::new(),
+            rule_id: None,
+            match_kind: None,
+            match_distance: None,
+            watchlisted: false,
         };
 
         let message = service.format_message(&event);
         assert!(message.contains("ABC123"));
         assert!(message.contains("95.0%"));
         assert!(message.contains("⚠️ Suspicious"));
+        assert!(!message.contains("Uncertain"));
+        assert!(!message.contains("Read:"));
+        assert!(message.contains("Type: Taxi"));
+    }
+
+    #[test]
+    fn test_unreadable_plate_formatting() {
+        let service = NotificationService::new(None, None, None);
+        let event = DetectionEvent {
+            timestamp: Utc::now(),
+            plate_number: String::new(),
+            confidence: 0.0,
+            image_path: "test.jpg".into(),
+            access_status: AccessStatus::Suspicious,
+            read_status: ReadStatus::Empty,
+            plate_type: PlateType::Unknown,
+            char_confidences: Vec::new(),
+            reason: String::new(),
+            rule_id: None,
+            match_kind: None,
+            match_distance: None,
+            watchlisted: false,
+        };
+
+        let message = service.format_message(&event);
+        assert!(message.contains("Plate: (unreadable)"));
+        assert!(message.contains("Read: No text recognized"));
+    }
+
+    #[test]
+    fn test_uncertain_chars_marked() {
+        let service = NotificationService::new(None, None, None);
+        let event = DetectionEvent {
+            timestamp: Utc::now(),
+            plate_number: "ABC123".into(),
+            confidence: 0.8,
+            image_path: "test.jpg".into(),
+            access_status: AccessStatus::Suspicious,
+            read_status: ReadStatus::LowConfidence,
+            plate_type: PlateType::Private,
+            char_confidences: vec![0.9, 0.3, 0.9, 0.9, 0.5, 0.9],
+            reason: String::new(),
+            rule_id: None,
+            match_kind: None,
+            match_distance: None,
+            watchlisted: false,
+        };
+
+        let message = service.format_message(&event);
+        assert!(message.contains("Uncertain: A?C1?3"));
+    }
+
+    #[test]
+    fn test_priority_message_formatting() {
+        let service = NotificationService::new(None, None, None);
+        let event = DetectionEvent {
+            timestamp: Utc::now(),
+            plate_number: "KK5555".into(),
+            confidence: 0.9,
+            image_path: "test.jpg".into(),
+            access_status: AccessStatus::Denied,
+            read_status: ReadStatus::Valid,
+            plate_type: PlateType::Private,
+            char_confidences: Vec::new(),
+            reason: "reported stolen: police report 2024/118".into(),
+            rule_id: Some("watchlist".into()),
+            match_kind: None,
+            match_distance: None,
+            watchlisted: true,
+        };
+
+        let message = service.format_priority_message(&event);
+        assert!(message.starts_with("🚨 WATCHLIST ALERT"));
+        assert!(message.contains("Reason: reported stolen: police report 2024/118"));
+        assert!(message.contains("❌ Denied"));
+        assert_ne!(message, service.format_message(&event));
     }
 
     #[tokio::test]
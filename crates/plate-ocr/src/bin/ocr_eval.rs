@@ -0,0 +1,81 @@
+//! Evaluates OCR accuracy over a folder of labeled plate crops.
+//!
+//! Usage:
+//!     ocr_eval <dataset_dir> <ground_truth.csv|json> [--report report.json]
+//!              [--engine tesseract|segmentation] [--preprocessing steps.json]
+//!              [--classifier classifier.json]
+
+use std::error::Error;
+use std::path::PathBuf;
+use tracing::{info, Level};
+use tracing_subscriber::FmtSubscriber;
+
+use plate_ocr::eval::{evaluate, load_ground_truth};
+use plate_ocr::{CharClassifier, CharClassifierConfig, PlateOcr, PreprocessConfig};
+
+const USAGE: &str = "Usage: ocr_eval <dataset_dir> <ground_truth> [--report <path>] \
+                     [--engine tesseract|segmentation] [--preprocessing <path>] [--classifier <path>]";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    FmtSubscriber::builder().with_max_level(Level::INFO).init();
+
+    let mut positional = Vec::new();
+    let mut report_path = PathBuf::from("ocr_eval_report.json");
+    let mut engine = "tesseract".to_string();
+    let mut preprocessing: Option<PathBuf> = None;
+    let mut classifier: Option<PathBuf> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--report" => report_path = PathBuf::from(value()?),
+            "--engine" => engine = value()?,
+            "--preprocessing" => preprocessing = Some(PathBuf::from(value()?)),
+            "--classifier" => classifier = Some(PathBuf::from(value()?)),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let [dataset_dir, ground_truth] = positional.as_slice() else {
+        return Err(USAGE.into());
+    };
+
+    let mut ocr = PlateOcr::new()?;
+    if let Some(path) = preprocessing {
+        let config: PreprocessConfig = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        ocr = ocr.with_preprocessing(config);
+    }
+    if let Some(path) = classifier {
+        let config: CharClassifierConfig = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        ocr = ocr.with_char_classifier(CharClassifier::from_config(&config)?);
+    }
+
+    let labels = load_ground_truth(ground_truth.as_ref())?;
+    info!("Loaded {} labels from {}", labels.len(), ground_truth);
+
+    let report = match engine.as_str() {
+        "tesseract" => evaluate(&engine, dataset_dir.as_ref(), &labels, |image| ocr.process_plate(image)),
+        "segmentation" => evaluate(&engine, dataset_dir.as_ref(), &labels, |image| {
+            ocr.process_plate_segmented(image)
+        }),
+        other => return Err(format!("Unknown engine '{}'\n{}", other, USAGE).into()),
+    };
+
+    report.write_json(&report_path)?;
+    println!(
+        "{}: {} samples, accuracy {:.2}%, CER {:.4}, ECE {:.4} -> {}",
+        report.engine,
+        report.overall.samples,
+        report.overall.accuracy * 100.0,
+        report.overall.character_error_rate,
+        report.expected_calibration_error,
+        report_path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+use image::GrayImage;
+use ndarray::{Array, CowArray};
+use ort::{Environment, GraphOptimizationLevel, Session, SessionBuilder, Value};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::OcrError;
+
+/// Number of alternatives kept per character.
+const TOP_K: usize = 3;
+
+/// Settings for loading a `CharClassifier` from configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharClassifierConfig {
+    pub model_path: PathBuf,
+    /// Output labels in model order, one character each, e.g. `"0123456789ABC..."`
+    pub labels: String,
+    #[serde(default = "default_input_size")]
+    pub input_width: u32,
+    #[serde(default = "default_input_size")]
+    pub input_height: u32,
+}
+
+fn default_input_size() -> u32 {
+    32
+}
+
+/// Small ONNX model that classifies a single normalized glyph.
+///
+/// The model takes a `1x1xHxW` float tensor in `[0, 1]` with text white on
+/// black (see `segment::normalize_glyph`) and outputs one score per label.
+/// Scores are passed through softmax, so both logits and probabilities work.
+pub struct CharClassifier {
+    session: Session,
+    labels: Vec<char>,
+    input_width: u32,
+    input_height: u32,
+}
+
+impl CharClassifier {
+    pub fn new<P: AsRef<Path>>(
+        model_path: P,
+        labels: Vec<char>,
+        input_width: u32,
+        input_height: u32,
+    ) -> Result<Self, OcrError> {
+        info!("Loading character classifier: {:?}", model_path.as_ref());
+
+        let environment = Environment::builder()
+            .with_name("YoloPlateSentry-char-classifier")
+            .build()
+            .map_err(|e| OcrError::ModelLoadError(e.to_string()))?
+            .into_arc();
+
+        let session = SessionBuilder::new(&environment)
+            .and_then(|b| b.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|b| b.with_model_from_file(model_path))
+            .map_err(|e| OcrError::ModelLoadError(format!("Failed to load classifier: {}", e)))?;
+
+        if labels.is_empty() {
+            return Err(OcrError::ModelLoadError(
+                "Character classifier needs at least one label".into(),
+            ));
+        }
+
+        Ok(Self {
+            session,
+            labels,
+            input_width,
+            input_height,
+        })
+    }
+
+    pub fn from_config(config: &CharClassifierConfig) -> Result<Self, OcrError> {
+        Self::new(
+            &config.model_path,
+            config.labels.chars().collect(),
+            config.input_width,
+            config.input_height,
+        )
+    }
+
+    pub fn input_size(&self) -> (u32, u32) {
+        (self.input_width, self.input_height)
+    }
+
+    /// Classifies a glyph already normalized to `input_size()`, returning the
+    /// best labels with their probabilities, best first.
+    pub fn classify(&self, glyph: &GrayImage) -> Result<Vec<(char, f32)>, OcrError> {
+        let data: Vec<f32> = glyph.pixels().map(|p| p[0] as f32 / 255.0).collect();
+        let array = CowArray::from(
+            Array::from_shape_vec(
+                (1, 1, self.input_height as usize, self.input_width as usize),
+                data,
+            )
+            .map_err(|e| OcrError::ImageProcessError(e.to_string()))?
+            .into_dyn(),
+        );
+
+        let input = Value::from_array(self.session.allocator(), &array)
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        let outputs = self
+            .session
+            .run(vec![input])
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        let scores = outputs[0]
+            .try_extract::<f32>()
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        let scores: Vec<f32> = scores.view().iter().copied().collect();
+
+        Ok(top_k(&softmax(&scores), &self.labels, TOP_K))
+    }
+}
+
+fn softmax(scores: &[f32]) -> Vec<f32> {
+    let max = scores.iter().copied().fold(f32::MIN, f32::max);
+    let exp: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.iter().map(|e| e / sum.max(f32::EPSILON)).collect()
+}
+
+fn top_k(probabilities: &[f32], labels: &[char], k: usize) -> Vec<(char, f32)> {
+    let mut ranked: Vec<(char, f32)> = labels.iter().copied().zip(probabilities.iter().copied()).collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked.truncate(k);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_softmax_top_k() {
+        let probabilities = softmax(&[1.0, 3.0, 2.0]);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+        let ranked = top_k(&probabilities, &['A', 'B', 'C'], 2);
+        assert_eq!(ranked.iter().map(|r| r.0).collect::<Vec<_>>(), vec!['B', 'C']);
+    }
+}
@@ -0,0 +1,174 @@
+use image::{GrayImage, Luma};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use imageproc::region_labelling::{connected_components, Connectivity};
+
+/// Pixels with a gradient magnitude above this are treated as edges when estimating skew.
+const EDGE_THRESHOLD: u16 = 120;
+
+/// Estimates plate rotation in degrees with a projection profile search.
+///
+/// Edge pixels are projected onto the vertical axis for every candidate angle
+/// in `[-max_angle, max_angle]`; text rows produce the sharpest profile when
+/// the plate is level. A positive result means the plate is rotated clockwise.
+pub fn estimate_skew(image: &GrayImage, max_angle: f32, step: f32) -> f32 {
+    let edges = imageproc::gradients::sobel_gradients(image);
+    let points: Vec<(f32, f32)> = edges
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p[0] > EDGE_THRESHOLD)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+
+    if points.len() < 16 || step <= 0.0 {
+        return 0.0;
+    }
+
+    let (w, h) = image.dimensions();
+    let bins = (w + h) as usize * 2;
+    let offset = w as f32;
+
+    let mut best_angle = 0.0;
+    let mut best_score = f64::MIN;
+    let steps = (max_angle.abs() / step).round() as i32;
+
+    for i in -steps..=steps {
+        let angle = i as f32 * step;
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut histogram = vec![0u32; bins];
+        for (x, y) in &points {
+            let row = (y * cos - x * sin + offset).round();
+            if row >= 0.0 && (row as usize) < bins {
+                histogram[row as usize] += 1;
+            }
+        }
+        let score: f64 = histogram.iter().map(|c| (*c as f64).powi(2)).sum();
+        if score > best_score {
+            best_score = score;
+            best_angle = angle;
+        }
+    }
+
+    best_angle
+}
+
+/// Rotates the plate so that its text rows are horizontal.
+pub fn deskew(image: &GrayImage, max_angle: f32, step: f32) -> GrayImage {
+    let angle = estimate_skew(image, max_angle, step);
+    if angle.abs() < step.max(f32::EPSILON) {
+        return image.clone();
+    }
+
+    let fill = border_median(image);
+    rotate_about_center(
+        image,
+        -angle.to_radians(),
+        Interpolation::Bilinear,
+        Luma([fill]),
+    )
+}
+
+/// Crops the plate to the bounding box of its character-like blobs.
+///
+/// Blobs touching the crop edge (plate frame, bumper) and blobs that are too
+/// short or too wide to be characters (screws, dirt, frame bars) are ignored.
+/// The image is returned unchanged when fewer than two characters are found.
+pub fn crop_to_characters(image: &GrayImage, margin: u32) -> GrayImage {
+    let (w, h) = image.dimensions();
+    if w < 8 || h < 8 {
+        return image.clone();
+    }
+
+    let binary = text_mask(image);
+    let labels = connected_components(&binary, Connectivity::Eight, Luma([0u8]));
+
+    // Bounding box per label: (x_min, y_min, x_max, y_max)
+    let mut boxes: std::collections::HashMap<u32, (u32, u32, u32, u32)> = Default::default();
+    for (x, y, label) in labels.enumerate_pixels() {
+        if label[0] == 0 {
+            continue;
+        }
+        let entry = boxes.entry(label[0]).or_insert((x, y, x, y));
+        entry.0 = entry.0.min(x);
+        entry.1 = entry.1.min(y);
+        entry.2 = entry.2.max(x);
+        entry.3 = entry.3.max(y);
+    }
+
+    let characters: Vec<_> = boxes
+        .values()
+        .filter(|(x0, y0, x1, y1)| {
+            let (bw, bh) = (x1 - x0 + 1, y1 - y0 + 1);
+            let touches_edge = *x0 == 0 || *y0 == 0 || *x1 == w - 1 || *y1 == h - 1;
+            !touches_edge && bh * 4 >= h && bh < h && bw * 2 < w && bw <= bh * 2
+        })
+        .collect();
+
+    if characters.len() < 2 {
+        return image.clone();
+    }
+
+    let x0 = characters.iter().map(|b| b.0).min().unwrap_or(0).saturating_sub(margin);
+    let y0 = characters.iter().map(|b| b.1).min().unwrap_or(0).saturating_sub(margin);
+    let x1 = (characters.iter().map(|b| b.2).max().unwrap_or(w - 1) + margin).min(w - 1);
+    let y1 = (characters.iter().map(|b| b.3).max().unwrap_or(h - 1) + margin).min(h - 1);
+
+    image::imageops::crop_imm(image, x0, y0, x1 - x0 + 1, y1 - y0 + 1).to_image()
+}
+
+/// Binarizes with Otsu and makes the minority (text) pixels the foreground.
+pub(crate) fn text_mask(image: &GrayImage) -> GrayImage {
+    let level = imageproc::contrast::otsu_level(image);
+    let mut mask = imageproc::contrast::threshold(image, level);
+    let white = mask.pixels().filter(|p| p[0] > 0).count();
+    if white * 2 > mask.pixels().len() {
+        image::imageops::invert(&mut mask);
+    }
+    mask
+}
+
+fn border_median(image: &GrayImage) -> u8 {
+    let (w, h) = image.dimensions();
+    let mut values: Vec<u8> = image
+        .enumerate_pixels()
+        .filter(|(x, y, _)| *x == 0 || *y == 0 || *x == w - 1 || *y == h - 1)
+        .map(|(_, _, p)| p[0])
+        .collect();
+    if values.is_empty() {
+        return 255;
+    }
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    /// White plate with a dark frame and four dark "characters".
+    fn synthetic_plate() -> GrayImage {
+        let mut image = GrayImage::from_pixel(200, 80, Luma([220]));
+        draw_filled_rect_mut(&mut image, Rect::at(0, 0).of_size(200, 6), Luma([20]));
+        draw_filled_rect_mut(&mut image, Rect::at(0, 74).of_size(200, 6), Luma([20]));
+        for i in 0..4 {
+            draw_filled_rect_mut(&mut image, Rect::at(40 + i * 32, 20).of_size(18, 40), Luma([20]));
+        }
+        image
+    }
+
+    #[test]
+    fn test_estimate_skew_of_rotated_plate() {
+        let plate = synthetic_plate();
+        let rotated = rotate_about_center(&plate, 5f32.to_radians(), Interpolation::Bilinear, Luma([220]));
+
+        let angle = estimate_skew(&rotated, 10.0, 0.5);
+        assert!((angle - 5.0).abs() <= 1.0, "estimated {}", angle);
+        assert!(estimate_skew(&plate, 10.0, 0.5).abs() <= 0.5);
+    }
+
+    #[test]
+    fn test_crop_to_characters_removes_frame() {
+        let cropped = crop_to_characters(&synthetic_plate(), 2);
+        assert_eq!(cropped.dimensions(), (114 + 4, 40 + 4));
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{LicensePlateText, OcrError, PlateValidity};
+
+/// Number of equal-width confidence bins in the calibration curve.
+const CALIBRATION_BINS: usize = 10;
+
+/// Marker used in the confusion matrix for a missing or extra character.
+pub const GAP: &str = "<gap>";
+
+/// One labeled plate crop.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroundTruth {
+    /// Image path, relative to the dataset directory
+    pub file: String,
+    pub plate: String,
+    /// Free-form grouping such as camera, lighting or plate style
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// Loads labels from a JSON array of `GroundTruth` or a CSV file with
+/// `file,plate[,profile]` rows. A CSV header row starting with `file` is skipped.
+pub fn load_ground_truth(path: &Path) -> Result<Vec<GroundTruth>, OcrError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        OcrError::ProcessingError(format!("Failed to read ground truth {:?}: {}", path, e))
+    })?;
+
+    let is_json = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    if is_json {
+        return serde_json::from_str(&content)
+            .map_err(|e| OcrError::ProcessingError(format!("Invalid ground truth JSON: {}", e)));
+    }
+
+    let mut labels = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line_no == 0 && fields[0].eq_ignore_ascii_case("file") {
+            continue;
+        }
+        if fields.len() < 2 {
+            return Err(OcrError::ProcessingError(format!(
+                "Ground truth line {} needs at least file and plate",
+                line_no + 1
+            )));
+        }
+        labels.push(GroundTruth {
+            file: fields[0].to_string(),
+            plate: fields[1].to_string(),
+            profile: fields.get(2).filter(|p| !p.is_empty()).map(|p| p.to_string()),
+        });
+    }
+    Ok(labels)
+}
+
+/// Per-sample outcome, kept in the report so regressions can be traced to images.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SampleResult {
+    pub file: String,
+    pub expected: String,
+    pub predicted: String,
+    pub confidence: f32,
+    pub validity: Option<PlateValidity>,
+    pub strategy: String,
+    pub edit_distance: usize,
+    pub latency_ms: f64,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub samples: usize,
+    pub exact_matches: usize,
+    pub accuracy: f64,
+    pub character_error_rate: f64,
+    pub mean_confidence: f64,
+    pub mean_latency_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationBin {
+    pub lower: f32,
+    pub upper: f32,
+    pub samples: usize,
+    pub mean_confidence: f64,
+    pub accuracy: f64,
+}
+
+/// Full evaluation report, written as JSON so runs can be diffed across releases.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub engine: String,
+    pub overall: Summary,
+    pub profiles: BTreeMap<String, Summary>,
+    /// Expected character -> predicted character -> count, with `GAP` for insertions/deletions
+    pub confusion: BTreeMap<String, BTreeMap<String, u64>>,
+    pub calibration: Vec<CalibrationBin>,
+    /// Expected calibration error over `calibration`
+    pub expected_calibration_error: f64,
+    pub validity_counts: BTreeMap<String, usize>,
+    pub samples: Vec<SampleResult>,
+}
+
+impl EvalReport {
+    pub fn write_json(&self, path: &Path) -> Result<(), OcrError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        std::fs::write(path, json)
+            .map_err(|e| OcrError::ProcessingError(format!("Failed to write report: {}", e)))
+    }
+}
+
+/// Runs `recognize` over every labeled image in `dataset_dir` and builds a report.
+///
+/// Images that fail to load are logged and skipped; recognition errors count
+/// as wrong empty reads so they show up in accuracy.
+pub fn evaluate<F>(
+    engine: &str,
+    dataset_dir: &Path,
+    labels: &[GroundTruth],
+    mut recognize: F,
+) -> EvalReport
+where
+    F: FnMut(&DynamicImage) -> Result<LicensePlateText, OcrError>,
+{
+    let mut samples = Vec::with_capacity(labels.len());
+    let mut profiles_by_file = Vec::with_capacity(labels.len());
+
+    for label in labels {
+        let path: PathBuf = dataset_dir.join(&label.file);
+        let image = match image::open(&path) {
+            Ok(image) => image,
+            Err(e) => {
+                warn!("Skipping {:?}: {}", path, e);
+                continue;
+            }
+        };
+
+        let expected = normalize(&label.plate);
+        let started = Instant::now();
+        let result = recognize(&image);
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let sample = match result {
+            Ok(read) => SampleResult {
+                file: label.file.clone(),
+                edit_distance: edit_distance(&expected, &read.processed_text),
+                expected,
+                predicted: read.processed_text,
+                confidence: read.confidence,
+                validity: Some(read.validity),
+                strategy: read.strategy,
+                latency_ms,
+                error: None,
+            },
+            Err(e) => SampleResult {
+                file: label.file.clone(),
+                edit_distance: expected.chars().count(),
+                expected,
+                predicted: String::new(),
+                confidence: 0.0,
+                validity: None,
+                strategy: String::new(),
+                latency_ms,
+                error: Some(e.to_string()),
+            },
+        };
+
+        samples.push(sample);
+        profiles_by_file.push(label.profile.clone());
+    }
+
+    let report = build_report(engine, samples, &profiles_by_file);
+    info!(
+        "Evaluated {} samples with {}: accuracy {:.2}%, CER {:.4}",
+        report.overall.samples,
+        engine,
+        report.overall.accuracy * 100.0,
+        report.overall.character_error_rate
+    );
+    report
+}
+
+fn build_report(engine: &str, samples: Vec<SampleResult>, profiles: &[Option<String>]) -> EvalReport {
+    let overall = summarize(samples.iter());
+
+    let mut grouped: BTreeMap<String, Vec<&SampleResult>> = BTreeMap::new();
+    for (sample, profile) in samples.iter().zip(profiles) {
+        let key = profile.clone().unwrap_or_else(|| "default".to_string());
+        grouped.entry(key).or_default().push(sample);
+    }
+    let profiles = grouped
+        .into_iter()
+        .map(|(name, group)| (name, summarize(group.into_iter())))
+        .collect();
+
+    let mut confusion: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+    for sample in &samples {
+        for (expected, predicted) in align(&sample.expected, &sample.predicted) {
+            *confusion
+                .entry(expected)
+                .or_default()
+                .entry(predicted)
+                .or_default() += 1;
+        }
+    }
+
+    let mut validity_counts = BTreeMap::new();
+    for sample in &samples {
+        let key = match &sample.validity {
+            Some(validity) => format!("{:?}", validity),
+            None => "Error".to_string(),
+        };
+        *validity_counts.entry(key).or_default() += 1;
+    }
+
+    let calibration = calibration_curve(&samples);
+    let expected_calibration_error = if samples.is_empty() {
+        0.0
+    } else {
+        calibration
+            .iter()
+            .map(|bin| bin.samples as f64 * (bin.accuracy - bin.mean_confidence).abs())
+            .sum::<f64>()
+            / samples.len() as f64
+    };
+
+    EvalReport {
+        engine: engine.to_string(),
+        overall,
+        profiles,
+        confusion,
+        calibration,
+        expected_calibration_error,
+        validity_counts,
+        samples,
+    }
+}
+
+fn summarize<'a>(samples: impl Iterator<Item = &'a SampleResult>) -> Summary {
+    let mut summary = Summary::default();
+    let (mut errors, mut characters) = (0usize, 0usize);
+    let (mut confidence, mut latency) = (0f64, 0f64);
+
+    for sample in samples {
+        summary.samples += 1;
+        if sample.expected == sample.predicted {
+            summary.exact_matches += 1;
+        }
+        errors += sample.edit_distance;
+        characters += sample.expected.chars().count();
+        confidence += sample.confidence as f64;
+        latency += sample.latency_ms;
+    }
+
+    if summary.samples > 0 {
+        let n = summary.samples as f64;
+        summary.accuracy = summary.exact_matches as f64 / n;
+        summary.mean_confidence = confidence / n;
+        summary.mean_latency_ms = latency / n;
+    }
+    summary.character_error_rate = errors as f64 / characters.max(1) as f64;
+    summary
+}
+
+fn calibration_curve(samples: &[SampleResult]) -> Vec<CalibrationBin> {
+    let mut bins: Vec<(usize, f64, usize)> = vec![(0, 0.0, 0); CALIBRATION_BINS];
+    for sample in samples {
+        let index = ((sample.confidence.clamp(0.0, 1.0) * CALIBRATION_BINS as f32) as usize)
+            .min(CALIBRATION_BINS - 1);
+        let bin = &mut bins[index];
+        bin.0 += 1;
+        bin.1 += sample.confidence as f64;
+        bin.2 += usize::from(sample.expected == sample.predicted);
+    }
+
+    bins.into_iter()
+        .enumerate()
+        .map(|(i, (count, confidence, correct))| CalibrationBin {
+            lower: i as f32 / CALIBRATION_BINS as f32,
+            upper: (i + 1) as f32 / CALIBRATION_BINS as f32,
+            samples: count,
+            mean_confidence: if count > 0 { confidence / count as f64 } else { 0.0 },
+            accuracy: if count > 0 { correct as f64 / count as f64 } else { 0.0 },
+        })
+        .collect()
+}
+
+fn normalize(plate: &str) -> String {
+    plate
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Levenshtein distance over characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Aligns expected and predicted text with a minimum edit path, returning
+/// (expected, predicted) character pairs with `GAP` for unmatched positions.
+fn align(expected: &str, predicted: &str) -> Vec<(String, String)> {
+    let a: Vec<char> = expected.chars().collect();
+    let b: Vec<char> = predicted.chars().collect();
+    let (n, m) = (a.len(), b.len());
+
+    let mut table = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            table[i][j] = (table[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                .min(table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && table[i][j] == table[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]) {
+            pairs.push((a[i - 1].to_string(), b[j - 1].to_string()));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && table[i][j] == table[i - 1][j] + 1 {
+            pairs.push((a[i - 1].to_string(), GAP.to_string()));
+            i -= 1;
+        } else {
+            pairs.push((GAP.to_string(), b[j - 1].to_string()));
+            j -= 1;
+        }
+    }
+    pairs.reverse();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(expected: &str, predicted: &str, confidence: f32) -> SampleResult {
+        SampleResult {
+            file: format!("{}.png", expected),
+            expected: expected.into(),
+            predicted: predicted.into(),
+            confidence,
+            validity: Some(PlateValidity::Valid),
+            strategy: "primary".into(),
+            edit_distance: edit_distance(expected, predicted),
+            latency_ms: 1.0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_edit_distance_and_alignment() {
+        assert_eq!(edit_distance("AB123", "AB123"), 0);
+        assert_eq!(edit_distance("AB123", "A8123"), 1);
+        assert_eq!(edit_distance("AB123", "AB12"), 1);
+
+        let pairs = align("AB12", "A812");
+        assert_eq!(pairs[1], ("B".to_string(), "8".to_string()));
+        let pairs = align("AB12", "AB1");
+        assert_eq!(pairs.last().unwrap(), &("2".to_string(), GAP.to_string()));
+    }
+
+    #[test]
+    fn test_report_metrics() {
+        let samples = vec![
+            sample("AB123", "AB123", 0.95),
+            sample("CD456", "C0456", 0.55),
+            sample("EF789", "EF789", 0.91),
+        ];
+        let profiles = vec![Some("day".into()), Some("night".into()), Some("day".into())];
+
+        let report = build_report("tesseract", samples, &profiles);
+        assert_eq!(report.overall.exact_matches, 2);
+        assert!((report.overall.character_error_rate - 1.0 / 15.0).abs() < 1e-9);
+        assert_eq!(report.profiles["day"].accuracy, 1.0);
+        assert_eq!(report.profiles["night"].accuracy, 0.0);
+        assert_eq!(report.confusion["D"]["0"], 1);
+        assert_eq!(report.calibration[9].samples, 2);
+        assert_eq!(report.calibration[5].accuracy, 0.0);
+    }
+
+    #[test]
+    fn test_load_csv_ground_truth() {
+        let path = std::env::temp_dir().join(format!("plate_ocr_gt_{}.csv", std::process::id()));
+        std::fs::write(&path, "file,plate,profile\na.png, AB 123 ,night\nb.png,CD456\n").unwrap();
+
+        let labels = load_ground_truth(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].plate, "AB 123");
+        assert_eq!(labels[0].profile.as_deref(), Some("night"));
+        assert_eq!(labels[1].profile, None);
+        assert_eq!(normalize(&labels[0].plate), "AB123");
+    }
+}
@@ -1,9 +1,40 @@
+use std::borrow::Cow;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use image::DynamicImage;
 use leptess::{tesseract::TessApi, LepTess};
 use regex::Regex;
 use thiserror::Error;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+pub mod classifier;
+pub mod deskew;
+pub mod eval;
+pub mod night;
+pub mod plate_type;
+pub mod preprocess;
+pub mod reading;
+pub mod segment;
+pub mod strategy;
+pub mod super_resolution;
+
+pub use classifier::{CharClassifier, CharClassifierConfig};
+pub use night::{FrameStats, Lighting, NightActivation, NightModeConfig, TimeOfDay};
+pub use plate_type::{PlateColor, PlateColors, PlateType};
+pub use preprocess::{MorphologyOp, PreprocessConfig, PreprocessStep, ThresholdMethod};
+pub use reading::{CharBox, CharReading, PlateCandidate};
+pub use strategy::{OcrStrategy, RetryConfig, PRIMARY_STRATEGY};
+pub use super_resolution::{SuperResolution, SuperResolutionConfig};
+
+/// Strategy name reported by the segmentation + classifier recognizer.
+pub const SEGMENTATION_STRATEGY: &str = "segmentation";
+
+/// Number of alternative plate strings kept by default.
+pub const DEFAULT_MAX_ALTERNATIVES: usize = 5;
+
+/// Reads below this mean confidence are reported as `PlateValidity::LowConfidence`.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 0.5;
 
 #[derive(Debug, Error)]
 pub enum OcrError {
@@ -13,8 +44,20 @@ pub enum OcrError {
     ImageProcessError(String),
     #[error("OCR processing error: {0}")]
     ProcessingError(String),
-    #[error("Invalid license plate format: {0}")]
-    ValidationError(String),
+    #[error("Failed to load model: {0}")]
+    ModelLoadError(String),
+}
+
+/// Outcome of validating a plate read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PlateValidity {
+    Valid,
+    /// Text was read but does not match the plate pattern
+    InvalidFormat,
+    /// No text was read at all
+    Empty,
+    /// Text matches the plate pattern but the engine was unsure of it
+    LowConfidence,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -22,11 +65,58 @@ pub struct LicensePlateText {
     pub text: String,
     pub confidence: f32,
     pub processed_text: String,  // Cleaned and formatted text
+    pub validity: PlateValidity,
+    /// Per-character readings, in reading order
+    #[serde(default)]
+    pub chars: Vec<CharReading>,
+    /// N best plate strings, best first; the first entry normally equals `processed_text`
+    #[serde(default)]
+    pub alternatives: Vec<PlateCandidate>,
+    /// Name of the attempt that produced this read, `PRIMARY_STRATEGY` for the first one
+    #[serde(default)]
+    pub strategy: String,
+    /// Vehicle category from the plate background colour
+    #[serde(default)]
+    pub plate_type: PlateType,
+    #[serde(default)]
+    pub background_color: PlateColor,
+    #[serde(default)]
+    pub text_color: PlateColor,
+}
+
+impl LicensePlateText {
+    pub fn is_valid(&self) -> bool {
+        self.validity == PlateValidity::Valid
+    }
+
+    fn with_colors(mut self, colors: PlateColors) -> Self {
+        self.plate_type = colors.plate_type;
+        self.background_color = colors.background;
+        self.text_color = colors.text;
+        self
+    }
+
+    /// Indices of characters whose confidence is below `threshold`.
+    pub fn uncertain_positions(&self, threshold: f32) -> Vec<usize> {
+        self.chars
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.confidence < threshold)
+            .map(|(i, _)| i)
+            .collect()
+    }
 }
 
 pub struct PlateOcr {
-    tesseract: LepTess,
+    tesseract: Mutex<LepTess>,
     plate_pattern: Regex,
+    max_alternatives: usize,
+    min_confidence: f32,
+    preprocessing: PreprocessConfig,
+    retry: RetryConfig,
+    char_classifier: Option<CharClassifier>,
+    super_resolution: Option<SuperResolution>,
+    night_mode: NightModeConfig,
 }
 
 impl PlateOcr {
@@ -43,6 +133,14 @@ impl PlateOcr {
             .set_variable("tessedit_char_whitelist", "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-")
             .map_err(|e| OcrError::TesseractInitError(e.to_string()))?;
 
+        // Emit per-character boxes/confidences and LSTM alternatives in hOCR output
+        tesseract
+            .set_variable("hocr_char_boxes", "1")
+            .map_err(|e| OcrError::TesseractInitError(e.to_string()))?;
+        tesseract
+            .set_variable("lstm_choice_mode", "2")
+            .map_err(|e| OcrError::TesseractInitError(e.to_string()))?;
+
         // Compile regex pattern for license plate validation
         // This is a basic pattern - adjust based on your specific license plate format
         let plate_pattern = Regex::new(r"^[A-Z0-9-]{4,10}$").map_err(|e| {
@@ -50,106 +148,401 @@ impl PlateOcr {
         })?;
 
         Ok(Self {
-            tesseract,
+            tesseract: Mutex::new(tesseract),
             plate_pattern,
+            max_alternatives: DEFAULT_MAX_ALTERNATIVES,
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            preprocessing: PreprocessConfig::default(),
+            retry: RetryConfig::default(),
+            char_classifier: None,
+            super_resolution: None,
+            night_mode: NightModeConfig::disabled(),
         })
     }
 
+    /// Upscales crops below the model's size threshold before preprocessing.
+    pub fn with_super_resolution(mut self, super_resolution: SuperResolution) -> Self {
+        self.super_resolution = Some(super_resolution);
+        self
+    }
+
+    /// Sets when and how night frames are preprocessed; see `lighting`.
+    pub fn with_night_mode(mut self, night_mode: NightModeConfig) -> Self {
+        self.night_mode = night_mode;
+        self
+    }
+
+    /// Decides whether a full camera frame captured at `time` should be read
+    /// with the night pipeline. Compute this once per frame, not per plate.
+    pub fn lighting(&self, frame: &DynamicImage, time: TimeOfDay) -> Lighting {
+        self.night_mode.lighting(frame, time)
+    }
+
+    /// Enables `process_plate_segmented` with the given per-character model.
+    pub fn with_char_classifier(mut self, classifier: CharClassifier) -> Self {
+        self.char_classifier = Some(classifier);
+        self
+    }
+
+    /// Replaces the fallback strategies tried when the first read is not valid.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Replaces the preprocessing pipeline applied before recognition.
+    pub fn with_preprocessing(mut self, preprocessing: PreprocessConfig) -> Self {
+        self.preprocessing = preprocessing;
+        self
+    }
+
+    /// Runs the pipeline a read used on a single plate and writes every
+    /// intermediate stage to `dir` as `<label>_<strategy>_<index>_<step>.png`,
+    /// for tuning the pipeline per camera. `strategy` is the name reported in
+    /// `LicensePlateText::strategy`; `label` should identify the plate.
+    pub fn dump_preprocessing<P: AsRef<Path>>(
+        &self,
+        image: &DynamicImage,
+        lighting: Lighting,
+        strategy: &str,
+        dir: P,
+        label: &str,
+    ) -> Result<DynamicImage, OcrError> {
+        let upscaled = self.upscale_if_tiny(image);
+        let label = format!("{}_{}", label, strategy);
+        let dir = dir.as_ref();
+        let stages = match strategy {
+            PRIMARY_STRATEGY => self.pipeline(lighting).run_and_dump(&upscaled, dir, &label),
+            SEGMENTATION_STRATEGY => self.preprocessing.run_and_dump(&upscaled, dir, &label),
+            name => {
+                let fallback = self.retry.strategies.iter().find(|s| s.name == name).ok_or_else(|| {
+                    OcrError::ProcessingError(format!("Unknown OCR strategy {}", name))
+                })?;
+                fallback
+                    .preprocessing(self.pipeline(lighting))
+                    .run_and_dump(&fallback.prepare_input(&upscaled), dir, &label)
+            }
+        };
+        stages.map(DynamicImage::ImageLuma8)
+    }
+
+    /// Sets the mean confidence below which a well-formed read is flagged as low confidence.
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets how many alternative plate strings are returned per read.
+    pub fn with_max_alternatives(mut self, max_alternatives: usize) -> Self {
+        self.max_alternatives = max_alternatives.max(1);
+        self
+    }
+
+    /// Reads a plate with the regular (day) pipeline.
     pub fn process_plate(&self, image: &DynamicImage) -> Result<LicensePlateText, OcrError> {
+        self.process_plate_with_lighting(image, Lighting::Day)
+    }
+
+    /// Reads a plate, retrying with the fallback strategies while the result
+    /// is not valid and the time budget allows. Returns the best attempt.
+    /// Night frames start from the night pipeline, and so do their fallbacks.
+    pub fn process_plate_with_lighting(
+        &self,
+        image: &DynamicImage,
+        lighting: Lighting,
+    ) -> Result<LicensePlateText, OcrError> {
+        let started = Instant::now();
+        let budget = Duration::from_millis(self.retry.time_budget_ms);
+        let upscaled = self.upscale_if_tiny(image);
+        let image: &DynamicImage = &upscaled;
+        let base = self.pipeline(lighting);
+
+        let mut best = self.attempt(image, base, None, PRIMARY_STRATEGY);
+
+        for strategy in &self.retry.strategies {
+            if matches!(&best, Ok(result) if result.is_valid()) {
+                break;
+            }
+            if started.elapsed() >= budget {
+                debug!("OCR time budget exhausted before strategy {}", strategy.name);
+                break;
+            }
+
+            let input = strategy.prepare_input(image);
+            let preprocessing = strategy.preprocessing(base);
+            let attempt = self.attempt(&input, &preprocessing, strategy.page_seg_mode, &strategy.name);
+
+            best = match (best, attempt) {
+                (Ok(current), Ok(candidate)) => {
+                    if strategy::rank(&candidate) > strategy::rank(&current) {
+                        Ok(candidate)
+                    } else {
+                        Ok(current)
+                    }
+                }
+                (Ok(current), Err(e)) => {
+                    debug!("OCR strategy {} failed: {}", strategy.name, e);
+                    Ok(current)
+                }
+                (Err(_), attempt) => attempt,
+            };
+        }
+
+        if let Ok(result) = &best {
+            debug!(
+                "OCR selected strategy {} ({:?}) after {:?}",
+                result.strategy,
+                lighting,
+                started.elapsed()
+            );
+        }
+
+        best.map(|result| result.with_colors(plate_type::classify_colors(image)))
+    }
+
+    /// Reads a plate without Tesseract: segments the binarized plate into
+    /// characters and classifies each one with the configured classifier.
+    /// Lines of two-row plates are read top to bottom.
+    pub fn process_plate_segmented(&self, image: &DynamicImage) -> Result<LicensePlateText, OcrError> {
+        let classifier = self.char_classifier.as_ref().ok_or_else(|| {
+            OcrError::ProcessingError("No character classifier configured".into())
+        })?;
+
+        let upscaled = self.upscale_if_tiny(image);
+        let binary = self.preprocessing.run(&upscaled)?;
+        let lines = segment::segment_characters(&binary);
+        let mask = deskew::text_mask(&binary);
+        let (input_width, input_height) = classifier.input_size();
+
+        let mut chars = Vec::new();
+        let mut text = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+            for bbox in line {
+                let glyph = segment::normalize_glyph(&mask, bbox, input_width, input_height);
+                let mut ranked = classifier.classify(&glyph)?.into_iter();
+                let Some((ch, confidence)) = ranked.next() else {
+                    continue;
+                };
+                text.push(ch);
+                chars.push(CharReading {
+                    ch,
+                    confidence,
+                    bbox: Some(*bbox),
+                    alternatives: ranked.collect(),
+                });
+            }
+        }
+
+        let confidence = if chars.is_empty() {
+            0.0
+        } else {
+            chars.iter().map(|c| c.confidence).sum::<f32>() / chars.len() as f32
+        };
+
+        let processed_text = self.postprocess_text(&text);
+        let validity = self.validate(&processed_text, confidence);
+        let alternatives = self.candidates(&chars, &processed_text, confidence);
+
+        debug!(
+            "Segmentation OCR - Lines: {}, Processed: {}, Confidence: {:.2}, Validity: {:?}",
+            lines.len(),
+            processed_text,
+            confidence,
+            validity
+        );
+
+        Ok(LicensePlateText {
+            text,
+            confidence,
+            processed_text,
+            validity,
+            chars,
+            alternatives,
+            strategy: SEGMENTATION_STRATEGY.to_string(),
+            plate_type: PlateType::Unknown,
+            background_color: PlateColor::Unknown,
+            text_color: PlateColor::Unknown,
+        }
+        .with_colors(plate_type::classify_colors(image)))
+    }
+
+    /// The base preprocessing pipeline for frames with this lighting.
+    fn pipeline(&self, lighting: Lighting) -> &PreprocessConfig {
+        match lighting {
+            Lighting::Day => &self.preprocessing,
+            Lighting::Night => &self.night_mode.preprocessing,
+        }
+    }
+
+    /// Runs the super-resolution model on tiny crops. Without a model, or if
+    /// it fails, the crop is returned as is and the pipeline's resize applies.
+    fn upscale_if_tiny<'a>(&self, image: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        match &self.super_resolution {
+            Some(model) if model.applies_to(image) => match model.upscale(image) {
+                Ok(upscaled) => Cow::Owned(upscaled),
+                Err(e) => {
+                    warn!("Super-resolution failed, using plain resize: {}", e);
+                    Cow::Borrowed(image)
+                }
+            },
+            _ => Cow::Borrowed(image),
+        }
+    }
+
+    /// Runs one preprocessing + recognition attempt.
+    fn attempt(
+        &self,
+        image: &DynamicImage,
+        preprocessing: &PreprocessConfig,
+        page_seg_mode: Option<u32>,
+        strategy: &str,
+    ) -> Result<LicensePlateText, OcrError> {
         // Preprocess image for better OCR accuracy
-        let processed_image = self.preprocess_image(image)?;
+        let processed_image = DynamicImage::ImageLuma8(preprocessing.run(image)?);
 
         // Convert image for Tesseract
         let width = processed_image.width() as i32;
         let height = processed_image.height() as i32;
         let bytes = processed_image.to_luma8().into_raw();
 
+        let mut tesseract = self
+            .tesseract
+            .lock()
+            .map_err(|_| OcrError::ProcessingError("Tesseract engine lock poisoned".into()))?;
+
+        // Remember the engine's own mode, so the override only lasts for this attempt
+        let previous_mode = match page_seg_mode {
+            Some(mode) => {
+                let previous = current_page_seg_mode(&tesseract)?;
+                tesseract
+                    .set_variable("tessedit_pageseg_mode", &mode.to_string())
+                    .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+                Some(previous)
+            }
+            None => None,
+        };
+
         // Set image data
-        self.tesseract
+        let recognized = tesseract
             .set_image_from_mem(&bytes, width, height, 1, width)
-            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))
+            .and_then(|_| {
+                // Perform OCR
+                tesseract
+                    .get_utf8_text()
+                    .map_err(|e| OcrError::ProcessingError(e.to_string()))
+            });
 
-        // Perform OCR
-        let text = self.tesseract
-            .get_utf8_text()
-            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
-
-        let confidence = self.tesseract
+        let confidence = tesseract
             .mean_text_conf() as f32 / 100.0;
 
+        // Per-character details come from the hOCR renderer
+        let chars = match recognized.is_ok().then(|| tesseract.get_hocr_text(0)) {
+            Some(Ok(hocr)) => reading::parse_hocr_chars(&hocr),
+            Some(Err(e)) => {
+                warn!("Failed to get hOCR output, per-character data unavailable: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        // Leave the engine in the mode it was in for the next attempt
+        if let Some(mode) = previous_mode {
+            tesseract
+                .set_variable("tessedit_pageseg_mode", &mode.to_string())
+                .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        }
+        drop(tesseract);
+
+        let text = recognized?;
+
         // Post-process and validate the text
-        let processed_text = self.postprocess_text(&text)?;
+        let processed_text = self.postprocess_text(&text);
+        let validity = self.validate(&processed_text, confidence);
+
+        let alternatives = self.candidates(&chars, &processed_text, confidence);
 
         debug!(
-            "OCR Result - Raw: {}, Processed: {}, Confidence: {:.2}",
+            "OCR Result ({}) - Raw: {}, Processed: {}, Confidence: {:.2}, Validity: {:?}, Alternatives: {}",
+            strategy,
             text.trim(),
             processed_text,
-            confidence
+            confidence,
+            validity,
+            alternatives.len()
         );
 
         Ok(LicensePlateText {
             text: text.trim().to_string(),
             confidence,
             processed_text,
+            validity,
+            chars,
+            alternatives,
+            strategy: strategy.to_string(),
+            plate_type: PlateType::Unknown,
+            background_color: PlateColor::Unknown,
+            text_color: PlateColor::Unknown,
         })
     }
 
-    fn preprocess_image(&self, image: &DynamicImage) -> Result<DynamicImage, OcrError> {
-        let mut processed = image.clone();
-
-        // Convert to grayscale
-        processed = DynamicImage::ImageLuma8(processed.to_luma8());
+    /// Builds the N-best list, keeping only strings that pass plate validation.
+    fn candidates(
+        &self,
+        chars: &[CharReading],
+        processed_text: &str,
+        confidence: f32,
+    ) -> Vec<PlateCandidate> {
+        let mut candidates: Vec<PlateCandidate> = reading::n_best(chars, self.max_alternatives)
+            .into_iter()
+            .filter(|c| self.plate_pattern.is_match(&c.text))
+            .collect();
 
-        // Resize if too small or too large
-        if processed.width() < 100 || processed.height() < 30 {
-            processed = processed.resize(
-                processed.width() * 2,
-                processed.height() * 2,
-                image::imageops::FilterType::Lanczos3,
-            );
-        } else if processed.width() > 1000 || processed.height() > 300 {
-            processed = processed.resize(
-                1000,
-                300,
-                image::imageops::FilterType::Lanczos3,
-            );
+        let primary_is_plate = self.plate_pattern.is_match(processed_text);
+        if primary_is_plate && !candidates.iter().any(|c| c.text == processed_text) {
+            candidates.insert(0, PlateCandidate {
+                text: processed_text.to_string(),
+                score: confidence,
+            });
+            candidates.truncate(self.max_alternatives);
         }
 
-        // Enhance contrast
-        processed = DynamicImage::ImageLuma8(
-            imageproc::contrast::stretch_contrast(&processed.to_luma8(), 50)
-        );
-
-        // Apply adaptive thresholding
-        let threshold = imageproc::contrast::threshold_adaptive(
-            &processed.to_luma8(),
-            15,  // block radius
-            |x| x as f32,
-        );
-        processed = DynamicImage::ImageLuma8(threshold);
-
-        Ok(processed)
+        candidates
     }
 
-    fn postprocess_text(&self, text: &str) -> Result<String, OcrError> {
+    fn postprocess_text(&self, text: &str) -> String {
         // Clean up the text
-        let processed = text
+        text
             .trim()
             .replace(['\n', ' '], "")
-            .to_uppercase();
-
-        // Validate against the pattern
-        if !self.plate_pattern.is_match(&processed) {
-            return Err(OcrError::ValidationError(format!(
-                "Text '{}' does not match license plate pattern",
-                processed
-            )));
-        }
+            .to_uppercase()
+    }
 
-        Ok(processed)
+    fn validate(&self, processed: &str, confidence: f32) -> PlateValidity {
+        if processed.is_empty() {
+            PlateValidity::Empty
+        } else if !self.plate_pattern.is_match(processed) {
+            PlateValidity::InvalidFormat
+        } else if confidence < self.min_confidence {
+            PlateValidity::LowConfidence
+        } else {
+            PlateValidity::Valid
+        }
     }
 }
 
+/// The engine's page segmentation mode. Only the command line tool defaults
+/// to 3; the API starts out in single block mode (6).
+fn current_page_seg_mode(tesseract: &LepTess) -> Result<u32, OcrError> {
+    tesseract
+        .get_int_variable("tessedit_pageseg_mode")
+        .map(|mode| mode as u32)
+        .ok_or_else(|| OcrError::ProcessingError("Failed to read tessedit_pageseg_mode".into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,19 +553,74 @@ mod tests {
         assert!(ocr.is_ok());
     }
 
+    #[test]
+    fn test_attempt_restores_page_seg_mode() {
+        let ocr = PlateOcr::new().unwrap();
+        let mode = || current_page_seg_mode(&ocr.tesseract.lock().unwrap()).unwrap();
+        let before = mode();
+
+        let plate = DynamicImage::new_luma8(120, 30);
+        let _ = ocr.attempt(&plate, &PreprocessConfig::default(), Some(7), "single-line");
+        assert_eq!(mode(), before);
+    }
+
     #[test]
     fn test_text_postprocessing() {
         let ocr = PlateOcr::new().unwrap();
         
+        assert_eq!(ocr.postprocess_text(" abc 123\n"), "ABC123");
+
         // Test valid plate number
-        assert!(ocr.postprocess_text("ABC123").is_ok());
-        
+        assert_eq!(ocr.validate("ABC123", 0.9), PlateValidity::Valid);
+
         // Test invalid plate number
-        assert!(ocr.postprocess_text("!@#$%^").is_err());
+        assert_eq!(ocr.validate("!@#$%^", 0.9), PlateValidity::InvalidFormat);
+        assert_eq!(ocr.validate("", 0.0), PlateValidity::Empty);
+        assert_eq!(ocr.validate("ABC123", 0.2), PlateValidity::LowConfidence);
+    }
+
+    #[test]
+    fn test_uncertain_positions() {
+        let chars = "AB12"
+            .chars()
+            .zip([0.95, 0.4, 0.9, 0.5])
+            .map(|(ch, confidence)| CharReading {
+                ch,
+                confidence,
+                bbox: None,
+                alternatives: Vec::new(),
+            })
+            .collect();
+        let plate = LicensePlateText {
+            text: "AB12".into(),
+            confidence: 0.69,
+            processed_text: "AB12".into(),
+            validity: PlateValidity::Valid,
+            chars,
+            alternatives: Vec::new(),
+            strategy: PRIMARY_STRATEGY.into(),
+            plate_type: PlateType::Private,
+            background_color: PlateColor::White,
+            text_color: PlateColor::Black,
+        };
+
+        assert_eq!(plate.uncertain_positions(0.6), vec![1, 3]);
     }
 
     #[test]
     fn test_image_preprocessing() {
-        // TODO: Add tests with sample images
+        // Dark bars on a light plate, as a synthetic stand-in for a plate crop
+        let plate = image::GrayImage::from_fn(160, 48, |x, y| {
+            let in_char = (12..36).contains(&y) && (x / 12) % 2 == 1 && (24..136).contains(&x);
+            image::Luma([if in_char { 30 } else { 210 }])
+        });
+        let ocr = PlateOcr::new().unwrap();
+        let processed = ocr
+            .preprocessing
+            .run(&DynamicImage::ImageLuma8(plate))
+            .unwrap();
+
+        assert!(processed.width() > 0 && processed.height() > 0);
+        assert!(processed.pixels().all(|p| p[0] == 0 || p[0] == 255));
     }
 }
\ No newline at end of file
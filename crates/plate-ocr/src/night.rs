@@ -0,0 +1,305 @@
+use std::fmt;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::preprocess::{PreprocessConfig, PreprocessStep, ThresholdMethod};
+
+/// Lighting condition a frame was captured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lighting {
+    #[default]
+    Day,
+    Night,
+}
+
+/// Time of day in minutes since midnight, written as `"HH:MM"` in config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(u32);
+
+impl TimeOfDay {
+    pub fn new(hour: u32, minute: u32) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self(hour * 60 + minute))
+    }
+
+    pub fn minutes(&self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (hour, minute) = value
+            .split_once(':')
+            .ok_or_else(|| format!("Expected HH:MM, got '{}'", value))?;
+        let hour = hour.trim().parse().map_err(|_| format!("Invalid hour in '{}'", value))?;
+        let minute = minute.trim().parse().map_err(|_| format!("Invalid minute in '{}'", value))?;
+        Self::new(hour, minute).ok_or_else(|| format!("Time out of range: '{}'", value))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+/// When the night pipeline is used instead of the regular one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum NightActivation {
+    /// Decide per frame from brightness statistics. A frame is treated as
+    /// night if it is both dark and nearly colourless; grey daytime scenes
+    /// are colourless too, but bright.
+    Auto {
+        #[serde(default = "default_max_mean_brightness")]
+        max_mean_brightness: f32,
+        #[serde(default = "default_max_colorfulness")]
+        max_colorfulness: f32,
+        /// The camera switches to IR at night. Its IR frames can be bright
+        /// where the illuminator hits, so being colourless is enough.
+        #[serde(default)]
+        ir_camera: bool,
+    },
+    /// Fixed local time window; may wrap past midnight, e.g. 18:30 to 06:00
+    Schedule { start: TimeOfDay, end: TimeOfDay },
+    Always,
+    Never,
+}
+
+fn default_max_mean_brightness() -> f32 {
+    0.25
+}
+
+fn default_max_colorfulness() -> f32 {
+    0.04
+}
+
+impl Default for NightActivation {
+    fn default() -> Self {
+        NightActivation::Auto {
+            max_mean_brightness: default_max_mean_brightness(),
+            max_colorfulness: default_max_colorfulness(),
+            ir_camera: false,
+        }
+    }
+}
+
+/// Night mode settings for one camera.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NightModeConfig {
+    #[serde(default)]
+    pub activation: NightActivation,
+    /// Pipeline used for night frames instead of the regular one
+    #[serde(default = "night_preprocessing")]
+    pub preprocessing: PreprocessConfig,
+}
+
+impl Default for NightModeConfig {
+    fn default() -> Self {
+        Self {
+            activation: NightActivation::default(),
+            preprocessing: night_preprocessing(),
+        }
+    }
+}
+
+impl NightModeConfig {
+    /// Night mode switched off; every frame uses the regular pipeline.
+    pub fn disabled() -> Self {
+        Self {
+            activation: NightActivation::Never,
+            ..Self::default()
+        }
+    }
+
+    /// Decides the lighting of a full camera frame captured at `time` local time.
+    pub fn lighting(&self, frame: &DynamicImage, time: TimeOfDay) -> Lighting {
+        let night = match &self.activation {
+            NightActivation::Auto {
+                max_mean_brightness,
+                max_colorfulness,
+                ir_camera,
+            } => {
+                let stats = FrameStats::from_image(frame);
+                stats.colorfulness <= *max_colorfulness && (*ir_camera || stats.mean_brightness <= *max_mean_brightness)
+            }
+            NightActivation::Schedule { start, end } => {
+                if start <= end {
+                    (*start..*end).contains(&time)
+                } else {
+                    time >= *start || time < *end
+                }
+            }
+            NightActivation::Always => true,
+            NightActivation::Never => false,
+        };
+
+        if night {
+            Lighting::Night
+        } else {
+            Lighting::Day
+        }
+    }
+}
+
+/// Glare suppression, retro-reflective stretch and local equalization ahead
+/// of a Sauvola threshold, which copes better with uneven IR illumination
+/// than a local mean.
+pub fn night_preprocessing() -> PreprocessConfig {
+    PreprocessConfig {
+        steps: vec![
+            PreprocessStep::Resize {
+                min_width: 100,
+                min_height: 30,
+                max_width: 1000,
+                max_height: 300,
+            },
+            PreprocessStep::Deskew {
+                max_angle: 15.0,
+                angle_step: 0.5,
+            },
+            PreprocessStep::CropToCharacters { margin: 4 },
+            PreprocessStep::SuppressGlare { sigma: 20.0 },
+            PreprocessStep::ReflectiveStretch {
+                threshold: 200,
+                min_fraction: 0.3,
+            },
+            PreprocessStep::Clahe {
+                tile_size: 16,
+                clip_limit: 3.0,
+            },
+            PreprocessStep::Denoise { radius: 1 },
+            PreprocessStep::Threshold(ThresholdMethod::Sauvola {
+                window_radius: 15,
+                k: 0.2,
+            }),
+        ],
+    }
+}
+
+/// Brightness statistics of a frame, all in `[0, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    pub mean_brightness: f32,
+    /// Fraction of pixels at or near full brightness
+    pub saturated_fraction: f32,
+    /// Mean per-pixel channel spread; close to zero for grayscale IR frames
+    pub colorfulness: f32,
+}
+
+impl FrameStats {
+    /// Pixels sampled along each axis; full frames don't need every pixel.
+    const SAMPLES: u32 = 160;
+
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let rgb = image.to_rgb8();
+        let (w, h) = rgb.dimensions();
+        if w == 0 || h == 0 {
+            return Self {
+                mean_brightness: 0.0,
+                saturated_fraction: 0.0,
+                colorfulness: 0.0,
+            };
+        }
+
+        let step_x = (w / Self::SAMPLES).max(1);
+        let step_y = (h / Self::SAMPLES).max(1);
+
+        let mut brightness = 0.0;
+        let mut spread = 0.0;
+        let mut saturated = 0usize;
+        let mut count = 0usize;
+        for y in (0..h).step_by(step_y as usize) {
+            for x in (0..w).step_by(step_x as usize) {
+                let [r, g, b] = rgb.get_pixel(x, y).0;
+                let luma = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) / 255.0;
+                let max = r.max(g).max(b);
+                let min = r.min(g).min(b);
+
+                brightness += luma;
+                spread += (max - min) as f32 / 255.0;
+                if luma >= 0.95 {
+                    saturated += 1;
+                }
+                count += 1;
+            }
+        }
+
+        let n = count as f32;
+        Self {
+            mean_brightness: brightness / n,
+            saturated_fraction: saturated as f32 / n,
+            colorfulness: spread / n,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_auto_activation_detects_dark_and_ir_frames() {
+        let config = NightModeConfig::default();
+        let noon = TimeOfDay::new(12, 0).unwrap();
+
+        let day = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, _| {
+            Rgb([120 + x as u8, 150, 90])
+        }));
+        assert_eq!(config.lighting(&day, noon), Lighting::Day);
+
+        let dark = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([30, 20, 25])));
+        assert_eq!(config.lighting(&dark, noon), Lighting::Night);
+
+        // Bright but colourless, as from an IR camera with the headlights on
+        let ir = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([180, 180, 180])));
+        let ir_camera: NightModeConfig =
+            serde_json::from_str(r#"{"activation": {"mode": "auto", "ir_camera": true}}"#).unwrap();
+        assert_eq!(ir_camera.lighting(&ir, noon), Lighting::Night);
+        assert_eq!(ir_camera.lighting(&day, noon), Lighting::Day);
+    }
+
+    #[test]
+    fn test_auto_activation_keeps_grey_daylight_frames() {
+        let config = NightModeConfig::default();
+        let noon = TimeOfDay::new(12, 0).unwrap();
+
+        // Overcast concrete forecourt with a silver car: bright, almost no colour
+        let overcast = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+            let v = 170 + ((x + y) % 40) as u8;
+            Rgb([v, v + 2, v + 5])
+        }));
+        let stats = FrameStats::from_image(&overcast);
+        assert!(stats.colorfulness <= 0.04 && stats.mean_brightness > 0.5, "{:?}", stats);
+        assert_eq!(config.lighting(&overcast, noon), Lighting::Day);
+    }
+
+    #[test]
+    fn test_schedule_wraps_midnight() {
+        let config: NightModeConfig = serde_json::from_str(
+            r#"{"activation": {"mode": "schedule", "start": "18:30", "end": "06:00"}}"#,
+        )
+        .unwrap();
+        let frame = DynamicImage::ImageRgb8(RgbImage::new(8, 8));
+
+        let at = |h, m| config.lighting(&frame, TimeOfDay::new(h, m).unwrap());
+        assert_eq!(at(18, 29), Lighting::Day);
+        assert_eq!(at(18, 30), Lighting::Night);
+        assert_eq!(at(2, 0), Lighting::Night);
+        assert_eq!(at(6, 0), Lighting::Day);
+
+        assert!(serde_json::from_str::<TimeOfDay>(r#""25:00""#).is_err());
+        assert_eq!(serde_json::to_string(&TimeOfDay::new(6, 5).unwrap()).unwrap(), r#""06:05""#);
+    }
+}
@@ -0,0 +1,191 @@
+use image::{DynamicImage, Rgb};
+use serde::{Deserialize, Serialize};
+
+/// Dominant colour of a plate region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlateColor {
+    White,
+    Black,
+    Yellow,
+    Green,
+    Blue,
+    Red,
+    Orange,
+    #[default]
+    Unknown,
+}
+
+/// Vehicle category encoded by the Thai plate background colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlateType {
+    /// White background
+    Private,
+    /// Yellow background
+    Taxi,
+    /// Green background
+    Commercial,
+    /// Blue background
+    Diplomat,
+    /// Red background, new vehicles awaiting registration
+    Temporary,
+    #[default]
+    Unknown,
+}
+
+impl PlateType {
+    pub fn from_background(background: PlateColor) -> Self {
+        match background {
+            PlateColor::White => PlateType::Private,
+            PlateColor::Yellow => PlateType::Taxi,
+            PlateColor::Green => PlateType::Commercial,
+            PlateColor::Blue => PlateType::Diplomat,
+            PlateColor::Red => PlateType::Temporary,
+            PlateColor::Black | PlateColor::Orange | PlateColor::Unknown => PlateType::Unknown,
+        }
+    }
+}
+
+/// Result of colour classification for one plate crop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PlateColors {
+    pub background: PlateColor,
+    pub text: PlateColor,
+    pub plate_type: PlateType,
+}
+
+/// Classifies background and text colour of a colour plate crop.
+///
+/// Pixels are split by Otsu on luminance; the larger group is the background
+/// and the smaller one the characters. Each group's mean colour is then
+/// named from its hue, saturation and value.
+pub fn classify_colors(image: &DynamicImage) -> PlateColors {
+    let rgb = image.to_rgb8();
+    if rgb.width() == 0 || rgb.height() == 0 {
+        return PlateColors::default();
+    }
+
+    let luma = image.to_luma8();
+    let level = imageproc::contrast::otsu_level(&luma);
+
+    let mut sums = [[0u64; 3]; 2];
+    let mut counts = [0u64; 2];
+    for (pixel, l) in rgb.pixels().zip(luma.pixels()) {
+        let group = usize::from(l[0] > level);
+        for c in 0..3 {
+            sums[group][c] += pixel[c] as u64;
+        }
+        counts[group] += 1;
+    }
+
+    let mean = |group: usize| {
+        let n = counts[group].max(1);
+        Rgb([
+            (sums[group][0] / n) as u8,
+            (sums[group][1] / n) as u8,
+            (sums[group][2] / n) as u8,
+        ])
+    };
+
+    let (background, text) = if counts[1] >= counts[0] {
+        (mean(1), mean(0))
+    } else {
+        (mean(0), mean(1))
+    };
+
+    let background = name_color(background);
+    PlateColors {
+        background,
+        text: name_color(text),
+        plate_type: PlateType::from_background(background),
+    }
+}
+
+/// Maps an RGB colour to the nearest plate colour name.
+pub fn name_color(color: Rgb<u8>) -> PlateColor {
+    let (h, s, v) = to_hsv(color);
+
+    if v < 0.25 {
+        PlateColor::Black
+    } else if s < 0.25 {
+        // Shaded or dirty white plates come out grey
+        if v > 0.45 {
+            PlateColor::White
+        } else {
+            PlateColor::Black
+        }
+    } else if !(15.0..345.0).contains(&h) {
+        PlateColor::Red
+    } else if h < 40.0 {
+        PlateColor::Orange
+    } else if h < 70.0 {
+        PlateColor::Yellow
+    } else if h < 170.0 {
+        PlateColor::Green
+    } else if h < 260.0 {
+        PlateColor::Blue
+    } else {
+        PlateColor::Unknown
+    }
+}
+
+fn to_hsv(color: Rgb<u8>) -> (f32, f32, f32) {
+    let [r, g, b] = color.0.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta <= f32::EPSILON {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max <= f32::EPSILON { 0.0 } else { delta / max };
+
+    (hue, saturation, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    fn plate(background: [u8; 3], text: [u8; 3]) -> DynamicImage {
+        let mut image = RgbImage::from_pixel(120, 40, Rgb(background));
+        for i in 0..4 {
+            draw_filled_rect_mut(&mut image, Rect::at(10 + i * 28, 8).of_size(10, 24), Rgb(text));
+        }
+        DynamicImage::ImageRgb8(image)
+    }
+
+    #[test]
+    fn test_classify_thai_plate_types() {
+        let private = classify_colors(&plate([235, 235, 235], [20, 20, 20]));
+        assert_eq!(private.plate_type, PlateType::Private);
+        assert_eq!(private.text, PlateColor::Black);
+
+        let taxi = classify_colors(&plate([240, 200, 30], [20, 20, 20]));
+        assert_eq!(taxi.plate_type, PlateType::Taxi);
+
+        let commercial = classify_colors(&plate([30, 140, 60], [240, 240, 240]));
+        assert_eq!(commercial.plate_type, PlateType::Commercial);
+        assert_eq!(commercial.text, PlateColor::White);
+
+        let diplomat = classify_colors(&plate([20, 60, 170], [240, 240, 240]));
+        assert_eq!(diplomat.plate_type, PlateType::Diplomat);
+    }
+
+    #[test]
+    fn test_name_color_blue_text_on_white() {
+        assert_eq!(name_color(Rgb([20, 40, 160])), PlateColor::Blue);
+        assert_eq!(name_color(Rgb([150, 150, 150])), PlateColor::White);
+        assert_eq!(name_color(Rgb([200, 30, 30])), PlateColor::Red);
+    }
+}
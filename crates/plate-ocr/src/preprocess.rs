@@ -0,0 +1,501 @@
+use std::path::Path;
+use image::{imageops::FilterType, DynamicImage, GrayImage, Luma};
+use imageproc::distance_transform::Norm;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{deskew, OcrError};
+
+/// Binarization method used by `PreprocessStep::Threshold`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ThresholdMethod {
+    /// Global threshold chosen by Otsu's method
+    Otsu,
+    /// Local mean threshold over a square window
+    Adaptive { block_radius: u32 },
+    /// Sauvola local threshold, robust to uneven illumination
+    Sauvola { window_radius: u32, k: f32 },
+    /// Fixed global threshold
+    Fixed { level: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MorphologyOp {
+    Erode,
+    Dilate,
+    Open,
+    Close,
+}
+
+/// A single preprocessing operation. Every step works on a grayscale image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum PreprocessStep {
+    /// Upscale crops smaller than the minimum size 2x, downscale crops larger than the maximum
+    Resize {
+        min_width: u32,
+        min_height: u32,
+        max_width: u32,
+        max_height: u32,
+    },
+    /// Rotate the plate level, searching skew angles up to `max_angle` degrees
+    Deskew { max_angle: f32, angle_step: f32 },
+    /// Crop to the character region, dropping plate frame, bumper and screws
+    CropToCharacters { margin: u32 },
+    /// Linearly map `[lower, upper]` to the full intensity range
+    ContrastStretch { lower: u8, upper: u8 },
+    /// Contrast limited adaptive histogram equalization
+    Clahe { tile_size: u32, clip_limit: f32 },
+    Gamma { gamma: f32 },
+    /// Median filter
+    Denoise { radius: u32 },
+    /// Unsharp mask
+    Sharpen { sigma: f32, amount: f32 },
+    Threshold(ThresholdMethod),
+    Morphology { op: MorphologyOp, radius: u8 },
+    /// Add a uniform border, which helps Tesseract with characters touching the edge
+    Pad { pixels: u32, value: u8 },
+    /// Invert intensities; with `only_if_dark` only plates with light text on a dark background are inverted
+    Invert { only_if_dark: bool },
+    /// Flatten uneven illumination such as headlight glare by dividing by a
+    /// heavily blurred copy of the image
+    SuppressGlare { sigma: f32 },
+    /// Stretch the bright range when retro-reflective plates saturate under IR,
+    /// applied only if at least `min_fraction` of pixels are at or above `threshold`
+    ReflectiveStretch { threshold: u8, min_fraction: f32 },
+}
+
+impl PreprocessStep {
+    /// Short name used for debug dump file names.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PreprocessStep::Resize { .. } => "resize",
+            PreprocessStep::Deskew { .. } => "deskew",
+            PreprocessStep::CropToCharacters { .. } => "crop_to_characters",
+            PreprocessStep::ContrastStretch { .. } => "contrast_stretch",
+            PreprocessStep::Clahe { .. } => "clahe",
+            PreprocessStep::Gamma { .. } => "gamma",
+            PreprocessStep::Denoise { .. } => "denoise",
+            PreprocessStep::Sharpen { .. } => "sharpen",
+            PreprocessStep::Threshold(_) => "threshold",
+            PreprocessStep::Morphology { .. } => "morphology",
+            PreprocessStep::Pad { .. } => "pad",
+            PreprocessStep::Invert { .. } => "invert",
+            PreprocessStep::SuppressGlare { .. } => "suppress_glare",
+            PreprocessStep::ReflectiveStretch { .. } => "reflective_stretch",
+        }
+    }
+
+    pub fn apply(&self, image: &GrayImage) -> GrayImage {
+        match self {
+            PreprocessStep::Resize {
+                min_width,
+                min_height,
+                max_width,
+                max_height,
+            } => resize(image, *min_width, *min_height, *max_width, *max_height),
+            PreprocessStep::Deskew {
+                max_angle,
+                angle_step,
+            } => deskew::deskew(image, *max_angle, *angle_step),
+            PreprocessStep::CropToCharacters { margin } => {
+                deskew::crop_to_characters(image, *margin)
+            }
+            PreprocessStep::ContrastStretch { lower, upper } => {
+                imageproc::contrast::stretch_contrast(image, *lower, (*upper).max(lower.saturating_add(1)))
+            }
+            PreprocessStep::Clahe {
+                tile_size,
+                clip_limit,
+            } => clahe(image, *tile_size, *clip_limit),
+            PreprocessStep::Gamma { gamma } => gamma_correct(image, *gamma),
+            PreprocessStep::Denoise { radius } => {
+                imageproc::filter::median_filter(image, *radius, *radius)
+            }
+            PreprocessStep::Sharpen { sigma, amount } => unsharp_mask(image, *sigma, *amount),
+            PreprocessStep::Threshold(method) => threshold(image, method),
+            PreprocessStep::Morphology { op, radius } => {
+                let k = *radius;
+                match op {
+                    MorphologyOp::Erode => imageproc::morphology::erode(image, Norm::LInf, k),
+                    MorphologyOp::Dilate => imageproc::morphology::dilate(image, Norm::LInf, k),
+                    MorphologyOp::Open => imageproc::morphology::open(image, Norm::LInf, k),
+                    MorphologyOp::Close => imageproc::morphology::close(image, Norm::LInf, k),
+                }
+            }
+            PreprocessStep::Pad { pixels, value } => pad(image, *pixels, *value),
+            PreprocessStep::Invert { only_if_dark } => {
+                if *only_if_dark && !is_dark_plate(image) {
+                    image.clone()
+                } else {
+                    let mut inverted = image.clone();
+                    image::imageops::invert(&mut inverted);
+                    inverted
+                }
+            }
+            PreprocessStep::SuppressGlare { sigma } => suppress_glare(image, *sigma),
+            PreprocessStep::ReflectiveStretch {
+                threshold,
+                min_fraction,
+            } => reflective_stretch(image, *threshold, *min_fraction),
+        }
+    }
+}
+
+/// Ordered list of preprocessing steps applied to every plate crop before OCR.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreprocessConfig {
+    pub steps: Vec<PreprocessStep>,
+}
+
+impl Default for PreprocessConfig {
+    /// Resize, level and crop the plate, then contrast stretch and adaptive threshold.
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                PreprocessStep::Resize {
+                    min_width: 100,
+                    min_height: 30,
+                    max_width: 1000,
+                    max_height: 300,
+                },
+                PreprocessStep::Deskew {
+                    max_angle: 15.0,
+                    angle_step: 0.5,
+                },
+                PreprocessStep::CropToCharacters { margin: 4 },
+                PreprocessStep::ContrastStretch {
+                    lower: 50,
+                    upper: 255,
+                },
+                PreprocessStep::Threshold(ThresholdMethod::Adaptive { block_radius: 15 }),
+            ],
+        }
+    }
+}
+
+impl PreprocessConfig {
+    /// Runs all steps on `image`.
+    pub fn run(&self, image: &DynamicImage) -> Result<GrayImage, OcrError> {
+        Ok(self
+            .steps
+            .iter()
+            .fold(image.to_luma8(), |img, step| step.apply(&img)))
+    }
+
+    /// Runs all steps and writes every intermediate stage as
+    /// `<dir>/<label>_<index>_<step>.png`, starting with the grayscale input.
+    pub fn run_and_dump(
+        &self,
+        image: &DynamicImage,
+        dir: &Path,
+        label: &str,
+    ) -> Result<GrayImage, OcrError> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            OcrError::ImageProcessError(format!("Failed to create dump directory: {}", e))
+        })?;
+
+        let mut current = image.to_luma8();
+        save_stage(&current, dir, label, 0, "input")?;
+
+        for (i, step) in self.steps.iter().enumerate() {
+            current = step.apply(&current);
+            save_stage(&current, dir, label, i + 1, step.name())?;
+        }
+
+        debug!("Dumped {} preprocessing stages to {:?}", self.steps.len() + 1, dir);
+        Ok(current)
+    }
+}
+
+fn save_stage(
+    image: &GrayImage,
+    dir: &Path,
+    label: &str,
+    index: usize,
+    name: &str,
+) -> Result<(), OcrError> {
+    let path = dir.join(format!("{}_{:02}_{}.png", label, index, name));
+    image
+        .save(&path)
+        .map_err(|e| OcrError::ImageProcessError(format!("Failed to save {:?}: {}", path, e)))
+}
+
+fn resize(image: &GrayImage, min_w: u32, min_h: u32, max_w: u32, max_h: u32) -> GrayImage {
+    let (w, h) = image.dimensions();
+    if w < min_w || h < min_h {
+        DynamicImage::ImageLuma8(image.clone())
+            .resize(w * 2, h * 2, FilterType::Lanczos3)
+            .to_luma8()
+    } else if w > max_w || h > max_h {
+        DynamicImage::ImageLuma8(image.clone())
+            .resize(max_w, max_h, FilterType::Lanczos3)
+            .to_luma8()
+    } else {
+        image.clone()
+    }
+}
+
+fn gamma_correct(image: &GrayImage, gamma: f32) -> GrayImage {
+    let gamma = if gamma > 0.0 { gamma } else { 1.0 };
+    let lut: Vec<u8> = (0..256)
+        .map(|v| ((v as f32 / 255.0).powf(1.0 / gamma) * 255.0).round() as u8)
+        .collect();
+    let mut out = image.clone();
+    out.pixels_mut().for_each(|p| p[0] = lut[p[0] as usize]);
+    out
+}
+
+fn unsharp_mask(image: &GrayImage, sigma: f32, amount: f32) -> GrayImage {
+    let blurred = imageproc::filter::gaussian_blur_f32(image, sigma.max(0.1));
+    let mut out = image.clone();
+    for (p, b) in out.pixels_mut().zip(blurred.pixels()) {
+        let v = p[0] as f32 + amount * (p[0] as f32 - b[0] as f32);
+        p[0] = v.clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
+fn suppress_glare(image: &GrayImage, sigma: f32) -> GrayImage {
+    let background = imageproc::filter::gaussian_blur_f32(image, sigma.max(1.0));
+    let mut out = image.clone();
+    for (p, b) in out.pixels_mut().zip(background.pixels()) {
+        let ratio = p[0] as f32 / (b[0] as f32 + 1.0);
+        p[0] = (ratio * 128.0).clamp(0.0, 255.0) as u8;
+    }
+    out
+}
+
+fn reflective_stretch(image: &GrayImage, threshold: u8, min_fraction: f32) -> GrayImage {
+    let total = image.pixels().len().max(1);
+    let bright = image.pixels().filter(|p| p[0] >= threshold).count();
+    if (bright as f32 / total as f32) < min_fraction || threshold == u8::MAX {
+        return image.clone();
+    }
+    imageproc::contrast::stretch_contrast(image, threshold, u8::MAX)
+}
+
+fn pad(image: &GrayImage, pixels: u32, value: u8) -> GrayImage {
+    let (w, h) = image.dimensions();
+    let mut out = GrayImage::from_pixel(w + 2 * pixels, h + 2 * pixels, Luma([value]));
+    image::imageops::replace(&mut out, image, pixels as i64, pixels as i64);
+    out
+}
+
+/// A plate is considered dark when its border, which is mostly background, is darker than its centre.
+pub(crate) fn is_dark_plate(image: &GrayImage) -> bool {
+    let (w, h) = image.dimensions();
+    if w < 4 || h < 4 {
+        return false;
+    }
+
+    let (mut border_sum, mut border_n, mut inner_sum, mut inner_n) = (0u64, 0u64, 0u64, 0u64);
+    let (bx, by) = (w / 8, h / 8);
+    for (x, y, p) in image.enumerate_pixels() {
+        if x < bx || x >= w - bx || y < by || y >= h - by {
+            border_sum += p[0] as u64;
+            border_n += 1;
+        } else {
+            inner_sum += p[0] as u64;
+            inner_n += 1;
+        }
+    }
+
+    let mean = (border_sum + inner_sum) as f32 / (border_n + inner_n).max(1) as f32;
+    let border_mean = border_sum as f32 / border_n.max(1) as f32;
+    mean < 128.0 && border_mean < mean + 8.0
+}
+
+fn threshold(image: &GrayImage, method: &ThresholdMethod) -> GrayImage {
+    match method {
+        ThresholdMethod::Otsu => {
+            let level = imageproc::contrast::otsu_level(image);
+            imageproc::contrast::threshold(image, level)
+        }
+        ThresholdMethod::Adaptive { block_radius } => {
+            imageproc::contrast::adaptive_threshold(image, *block_radius)
+        }
+        ThresholdMethod::Sauvola { window_radius, k } => sauvola(image, *window_radius, *k),
+        ThresholdMethod::Fixed { level } => imageproc::contrast::threshold(image, *level),
+    }
+}
+
+fn sauvola(image: &GrayImage, radius: u32, k: f32) -> GrayImage {
+    let (w, h) = image.dimensions();
+    let (wu, hu) = (w as usize, h as usize);
+    let stride = wu + 1;
+
+    // Integral images of values and squared values with a zero first row/column
+    let mut sum = vec![0f64; stride * (hu + 1)];
+    let mut sq = vec![0f64; stride * (hu + 1)];
+    for y in 0..hu {
+        let (mut row_sum, mut row_sq) = (0f64, 0f64);
+        for x in 0..wu {
+            let v = image.get_pixel(x as u32, y as u32)[0] as f64;
+            row_sum += v;
+            row_sq += v * v;
+            sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row_sum;
+            sq[(y + 1) * stride + x + 1] = sq[y * stride + x + 1] + row_sq;
+        }
+    }
+
+    let r = radius as usize;
+    let mut out = GrayImage::new(w, h);
+    for y in 0..hu {
+        let (y0, y1) = (y.saturating_sub(r), (y + r + 1).min(hu));
+        for x in 0..wu {
+            let (x0, x1) = (x.saturating_sub(r), (x + r + 1).min(wu));
+            let n = ((x1 - x0) * (y1 - y0)) as f64;
+            let area = |t: &[f64]| {
+                t[y1 * stride + x1] - t[y0 * stride + x1] - t[y1 * stride + x0] + t[y0 * stride + x0]
+            };
+            let mean = area(&sum) / n;
+            let variance = (area(&sq) / n - mean * mean).max(0.0);
+            let limit = mean * (1.0 + k as f64 * (variance.sqrt() / 128.0 - 1.0));
+            let v = image.get_pixel(x as u32, y as u32)[0] as f64;
+            out.put_pixel(x as u32, y as u32, Luma([if v > limit { 255 } else { 0 }]));
+        }
+    }
+    out
+}
+
+/// CLAHE with bilinear interpolation between tile mappings.
+pub(crate) fn clahe(image: &GrayImage, tile_size: u32, clip_limit: f32) -> GrayImage {
+    let (w, h) = image.dimensions();
+    let tile = tile_size.max(8);
+    let tiles_x = w.div_ceil(tile).max(1) as usize;
+    let tiles_y = h.div_ceil(tile).max(1) as usize;
+
+    // Per-tile equalization lookup tables
+    let mut luts = vec![[0u8; 256]; tiles_x * tiles_y];
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let x0 = tx as u32 * tile;
+            let y0 = ty as u32 * tile;
+            let x1 = (x0 + tile).min(w);
+            let y1 = (y0 + tile).min(h);
+
+            let mut hist = [0u32; 256];
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    hist[image.get_pixel(x, y)[0] as usize] += 1;
+                }
+            }
+
+            let n = ((x1 - x0) * (y1 - y0)).max(1);
+            let limit = ((clip_limit.max(1.0) * n as f32) / 256.0).max(1.0) as u32;
+            let mut excess = 0u32;
+            for count in hist.iter_mut() {
+                if *count > limit {
+                    excess += *count - limit;
+                    *count = limit;
+                }
+            }
+            let bonus = excess / 256;
+            let mut cdf = 0u32;
+            let lut = &mut luts[ty * tiles_x + tx];
+            for (v, count) in hist.iter().enumerate() {
+                cdf += count + bonus;
+                lut[v] = ((cdf as f32 / n as f32) * 255.0).min(255.0) as u8;
+            }
+        }
+    }
+
+    let mut out = GrayImage::new(w, h);
+    for (x, y, p) in image.enumerate_pixels() {
+        // Position relative to tile centres
+        let fx = (x as f32 + 0.5) / tile as f32 - 0.5;
+        let fy = (y as f32 + 0.5) / tile as f32 - 0.5;
+        let tx0 = (fx.floor().max(0.0) as usize).min(tiles_x - 1);
+        let ty0 = (fy.floor().max(0.0) as usize).min(tiles_y - 1);
+        let tx1 = (tx0 + 1).min(tiles_x - 1);
+        let ty1 = (ty0 + 1).min(tiles_y - 1);
+        let ax = (fx - tx0 as f32).clamp(0.0, 1.0);
+        let ay = (fy - ty0 as f32).clamp(0.0, 1.0);
+
+        let v = p[0] as usize;
+        let top = luts[ty0 * tiles_x + tx0][v] as f32 * (1.0 - ax) + luts[ty0 * tiles_x + tx1][v] as f32 * ax;
+        let bottom = luts[ty1 * tiles_x + tx0][v] as f32 * (1.0 - ax) + luts[ty1 * tiles_x + tx1][v] as f32 * ax;
+        out.put_pixel(x, y, Luma([(top * (1.0 - ay) + bottom * ay).round() as u8]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(w: u32, h: u32) -> GrayImage {
+        GrayImage::from_fn(w, h, |x, _| Luma([(x * 255 / w.max(1)) as u8]))
+    }
+
+    #[test]
+    fn test_default_pipeline_binarizes() {
+        let image = DynamicImage::ImageLuma8(gradient(200, 60));
+        let out = PreprocessConfig::default().run(&image).unwrap();
+        assert_eq!(out.dimensions(), (200, 60));
+        assert!(out.pixels().all(|p| p[0] == 0 || p[0] == 255));
+    }
+
+    #[test]
+    fn test_steps_deserialize_from_config() {
+        let json = r#"{
+            "steps": [
+                {"step": "clahe", "tile_size": 16, "clip_limit": 2.0},
+                {"step": "threshold", "method": "sauvola", "window_radius": 7, "k": 0.2},
+                {"step": "pad", "pixels": 4, "value": 255},
+                {"step": "invert", "only_if_dark": true}
+            ]
+        }"#;
+        let config: PreprocessConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.steps.len(), 4);
+
+        let out = config.run(&DynamicImage::ImageLuma8(gradient(64, 32))).unwrap();
+        assert_eq!(out.dimensions(), (72, 40));
+    }
+
+    #[test]
+    fn test_invert_only_dark_plates() {
+        let mut dark = GrayImage::from_pixel(40, 20, Luma([20]));
+        dark.put_pixel(20, 10, Luma([240]));
+        let light = GrayImage::from_pixel(40, 20, Luma([230]));
+        let step = PreprocessStep::Invert { only_if_dark: true };
+
+        assert_eq!(step.apply(&dark).get_pixel(0, 0)[0], 235);
+        assert_eq!(step.apply(&light).get_pixel(0, 0)[0], 230);
+    }
+
+    #[test]
+    fn test_reflective_stretch_recovers_washed_out_text() {
+        // Saturated plate where characters are only slightly darker
+        let mut plate = GrayImage::from_pixel(40, 20, Luma([250]));
+        plate.put_pixel(10, 10, Luma([225]));
+        let step = PreprocessStep::ReflectiveStretch {
+            threshold: 220,
+            min_fraction: 0.5,
+        };
+
+        let out = step.apply(&plate);
+        assert!(out.get_pixel(0, 0)[0] > 200);
+        assert!(out.get_pixel(10, 10)[0] < 50);
+
+        let dark = GrayImage::from_pixel(40, 20, Luma([60]));
+        assert_eq!(step.apply(&dark), dark);
+    }
+
+    #[test]
+    fn test_run_and_dump_writes_every_stage() {
+        let dir = std::env::temp_dir().join(format!("plate_ocr_dump_{}", std::process::id()));
+        let image = DynamicImage::ImageLuma8(gradient(120, 40));
+        PreprocessConfig::default()
+            .run_and_dump(&image, &dir, "plate")
+            .unwrap();
+
+        assert!(dir.join("plate_00_input.png").exists());
+        assert!(dir.join("plate_03_crop_to_characters.png").exists());
+        assert!(dir.join("plate_05_threshold.png").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
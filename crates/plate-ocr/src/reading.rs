@@ -0,0 +1,207 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Bounding box of a single character, in pixels of the image passed to the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CharBox {
+    pub x_min: u32,
+    pub y_min: u32,
+    pub x_max: u32,
+    pub y_max: u32,
+}
+
+/// One recognized character together with the engine's confidence and
+/// the other characters it considered for the same position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharReading {
+    pub ch: char,
+    pub confidence: f32,
+    pub bbox: Option<CharBox>,
+    /// Alternative characters with their confidences, best first.
+    #[serde(default)]
+    pub alternatives: Vec<(char, f32)>,
+}
+
+/// A complete plate string built from per-character choices.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlateCandidate {
+    pub text: String,
+    /// Geometric mean of the character confidences used to build `text`.
+    pub score: f32,
+}
+
+/// Builds the `n` most likely plate strings from per-character readings.
+///
+/// Every position contributes its primary character and its alternatives;
+/// candidates are ranked by the product of their character confidences.
+/// Any engine that can fill in `CharReading`s can use this.
+pub fn n_best(chars: &[CharReading], n: usize) -> Vec<PlateCandidate> {
+    if chars.is_empty() || n == 0 {
+        return Vec::new();
+    }
+
+    let mut beam: Vec<(String, f32)> = vec![(String::new(), 1.0)];
+
+    for reading in chars {
+        let mut options = vec![(reading.ch, reading.confidence)];
+        options.extend(
+            reading
+                .alternatives
+                .iter()
+                .filter(|(ch, _)| *ch != reading.ch)
+                .copied(),
+        );
+
+        let mut next = Vec::with_capacity(beam.len() * options.len());
+        for (prefix, score) in &beam {
+            for (ch, confidence) in &options {
+                let mut text = prefix.clone();
+                text.push(ch.to_ascii_uppercase());
+                next.push((text, score * confidence.clamp(0.0, 1.0)));
+            }
+        }
+
+        next.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        next.dedup_by(|a, b| a.0 == b.0);
+        next.truncate(n);
+        beam = next;
+    }
+
+    let exponent = 1.0 / chars.len() as f32;
+    beam.into_iter()
+        .map(|(text, product)| PlateCandidate {
+            text,
+            score: product.powf(exponent),
+        })
+        .collect()
+}
+
+static CHAR_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"class='ocrx_cinfo' title='x_bboxes (\d+) (\d+) (\d+) (\d+); x_conf ([\d.]+)'>([^<]+)</span>")
+        .expect("valid hOCR character pattern")
+});
+
+static GLYPH_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"class='ocr_glyph'[^>]*title='x_confs ([\d.]+)'>([^<]+)</span>").expect("valid hOCR glyph pattern")
+});
+
+/// Extracts per-character readings from Tesseract hOCR output.
+///
+/// Expects `hocr_char_boxes=1` for the `x_bboxes`/`x_conf` character spans
+/// and `lstm_choice_mode=2` for the `ocr_glyph` alternatives. Alternatives
+/// are only attached when every character of a word has a choice block,
+/// since otherwise they cannot be aligned reliably.
+pub(crate) fn parse_hocr_chars(hocr: &str) -> Vec<CharReading> {
+    let mut readings = Vec::new();
+
+    for word in hocr.split("class='ocrx_word'").skip(1) {
+        let mut word_chars: Vec<CharReading> = CHAR_PATTERN
+            .captures_iter(word)
+            .filter_map(|caps| {
+                let ch = unescape_html(&caps[6]).chars().next()?;
+                if ch.is_whitespace() {
+                    return None;
+                }
+                Some(CharReading {
+                    ch,
+                    confidence: caps[5].parse::<f32>().unwrap_or(0.0) / 100.0,
+                    bbox: Some(CharBox {
+                        x_min: caps[1].parse().unwrap_or(0),
+                        y_min: caps[2].parse().unwrap_or(0),
+                        x_max: caps[3].parse().unwrap_or(0),
+                        y_max: caps[4].parse().unwrap_or(0),
+                    }),
+                    alternatives: Vec::new(),
+                })
+            })
+            .collect();
+
+        let choice_blocks: Vec<Vec<(char, f32)>> = word
+            .split("id='lstm_choices_")
+            .skip(1)
+            .map(|block| {
+                let mut glyphs: Vec<(char, f32)> = GLYPH_PATTERN
+                    .captures_iter(block)
+                    .filter_map(|caps| {
+                        let ch = unescape_html(&caps[2]).chars().next()?;
+                        Some((ch, caps[1].parse::<f32>().unwrap_or(0.0) / 100.0))
+                    })
+                    .collect();
+                glyphs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+                glyphs
+            })
+            .collect();
+
+        if choice_blocks.len() == word_chars.len() {
+            for (reading, glyphs) in word_chars.iter_mut().zip(choice_blocks) {
+                reading.alternatives = glyphs
+                    .into_iter()
+                    .filter(|(ch, _)| *ch != reading.ch)
+                    .collect();
+            }
+        }
+
+        readings.append(&mut word_chars);
+    }
+
+    readings
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(ch: char, confidence: f32, alternatives: Vec<(char, f32)>) -> CharReading {
+        CharReading {
+            ch,
+            confidence,
+            bbox: None,
+            alternatives,
+        }
+    }
+
+    #[test]
+    fn test_n_best_orders_by_score() {
+        let chars = vec![
+            reading('A', 0.9, vec![]),
+            reading('8', 0.6, vec![('B', 0.4)]),
+            reading('1', 0.95, vec![]),
+        ];
+
+        let candidates = n_best(&chars, 3);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].text, "A81");
+        assert_eq!(candidates[1].text, "AB1");
+        assert!(candidates[0].score > candidates[1].score);
+    }
+
+    #[test]
+    fn test_parse_hocr_chars_with_choices() {
+        let hocr = "<span class='ocrx_word' id='word_1_1' title='bbox 0 0 40 20; x_wconf 80'>\
+            <span class='ocrx_cinfo' title='x_bboxes 0 0 10 20; x_conf 91.5'>A</span>\
+            <span class='ocrx_cinfo' title='x_bboxes 12 0 22 20; x_conf 55'>8</span>\
+            <span class='ocrx_cinfo' id='lstm_choices_1_1_1'>\
+            <span class='ocr_glyph' id='choice_1_1_1' title='x_confs 91'>A</span></span>\
+            <span class='ocrx_cinfo' id='lstm_choices_1_1_2'>\
+            <span class='ocr_glyph' id='choice_1_1_2' title='x_confs 55'>8</span>\
+            <span class='ocr_glyph' id='choice_1_1_3' title='x_confs 40'>B</span></span>\
+            </span>";
+
+        let chars = parse_hocr_chars(hocr);
+        assert_eq!(chars.len(), 2);
+        assert_eq!(chars[0].ch, 'A');
+        assert!((chars[0].confidence - 0.915).abs() < 1e-4);
+        assert_eq!(chars[1].bbox.unwrap().x_min, 12);
+        assert_eq!(chars[1].alternatives, vec![('B', 0.4)]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use image::{GrayImage, Luma};
+use imageproc::region_labelling::{connected_components, Connectivity};
+
+use crate::deskew::text_mask;
+use crate::reading::CharBox;
+
+/// Components smaller than this many pixels are treated as noise.
+const MIN_COMPONENT_AREA: u32 = 12;
+
+/// Splits a binarized plate into character boxes grouped by text line.
+///
+/// Lines are returned top to bottom and characters left to right, so
+/// two-row plates yield two lines. Whichever polarity has fewer pixels is
+/// treated as text.
+pub fn segment_characters(binary: &GrayImage) -> Vec<Vec<CharBox>> {
+    let (w, h) = binary.dimensions();
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+
+    let mask = text_mask(binary);
+    let labels = connected_components(&mask, Connectivity::Eight, Luma([0u8]));
+
+    let mut components: HashMap<u32, (CharBox, u32)> = HashMap::new();
+    for (x, y, label) in labels.enumerate_pixels() {
+        if label[0] == 0 {
+            continue;
+        }
+        let (bbox, area) = components.entry(label[0]).or_insert((
+            CharBox {
+                x_min: x,
+                y_min: y,
+                x_max: x,
+                y_max: y,
+            },
+            0,
+        ));
+        bbox.x_min = bbox.x_min.min(x);
+        bbox.y_min = bbox.y_min.min(y);
+        bbox.x_max = bbox.x_max.max(x);
+        bbox.y_max = bbox.y_max.max(y);
+        *area += 1;
+    }
+
+    let boxes: Vec<CharBox> = components
+        .into_values()
+        .filter(|(b, area)| {
+            let (bw, bh) = (b.x_max - b.x_min + 1, b.y_max - b.y_min + 1);
+            *area >= MIN_COMPONENT_AREA && bh * 8 >= h && bh * 20 < h * 19 && bw * 2 <= bh * 3
+        })
+        .map(|(b, _)| b)
+        .collect();
+
+    group_lines(boxes)
+}
+
+/// Groups boxes into lines by vertical overlap, then orders them for reading.
+pub(crate) fn group_lines(mut boxes: Vec<CharBox>) -> Vec<Vec<CharBox>> {
+    boxes.sort_by_key(|b| b.y_min + b.y_max);
+
+    let mut lines: Vec<Vec<CharBox>> = Vec::new();
+    for bbox in boxes {
+        let centre = (bbox.y_min + bbox.y_max) / 2;
+        let line = lines.iter_mut().find(|line| {
+            let top = line.iter().map(|b| b.y_min).min().unwrap_or(0);
+            let bottom = line.iter().map(|b| b.y_max).max().unwrap_or(0);
+            centre >= top && centre <= bottom
+        });
+        match line {
+            Some(line) => line.push(bbox),
+            None => lines.push(vec![bbox]),
+        }
+    }
+
+    for line in lines.iter_mut() {
+        line.sort_by_key(|b| b.x_min);
+    }
+    lines.sort_by_key(|line| line.iter().map(|b| b.y_min).min().unwrap_or(0));
+    lines
+}
+
+/// Cuts one character out of the plate's text mask (text white on black,
+/// computed once per plate), centred on a square canvas and resized to
+/// `width` x `height`.
+pub fn normalize_glyph(mask: &GrayImage, bbox: &CharBox, width: u32, height: u32) -> GrayImage {
+    let (bw, bh) = (bbox.x_max - bbox.x_min + 1, bbox.y_max - bbox.y_min + 1);
+    let glyph = image::imageops::crop_imm(mask, bbox.x_min, bbox.y_min, bw, bh).to_image();
+
+    let side = bw.max(bh) + 2;
+    let mut canvas = GrayImage::new(side, side);
+    image::imageops::replace(
+        &mut canvas,
+        &glyph,
+        ((side - bw) / 2) as i64,
+        ((side - bh) / 2) as i64,
+    );
+
+    image::imageops::resize(&canvas, width, height, image::imageops::FilterType::Triangle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imageproc::drawing::draw_filled_rect_mut;
+    use imageproc::rect::Rect;
+
+    #[test]
+    fn test_two_row_plate_segmentation() {
+        let mut plate = GrayImage::from_pixel(160, 100, Luma([255]));
+        // Top row: three tall characters
+        for i in 0..3 {
+            draw_filled_rect_mut(&mut plate, Rect::at(20 + i * 40, 10).of_size(20, 40), Luma([0]));
+        }
+        // Bottom row: two smaller characters
+        for i in 0..2 {
+            draw_filled_rect_mut(&mut plate, Rect::at(40 + i * 40, 65).of_size(16, 25), Luma([0]));
+        }
+        // Screw hole, too small to be a character
+        draw_filled_rect_mut(&mut plate, Rect::at(4, 4).of_size(3, 3), Luma([0]));
+
+        let lines = segment_characters(&plate);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 3);
+        assert_eq!(lines[1].len(), 2);
+        assert!(lines[0].windows(2).all(|w| w[0].x_min < w[1].x_min));
+        assert_eq!(lines[1][0].x_min, 40);
+    }
+
+    #[test]
+    fn test_normalize_glyph_is_white_on_black() {
+        let mut plate = GrayImage::from_pixel(60, 40, Luma([255]));
+        draw_filled_rect_mut(&mut plate, Rect::at(10, 5).of_size(10, 30), Luma([0]));
+        let bbox = CharBox {
+            x_min: 10,
+            y_min: 5,
+            x_max: 19,
+            y_max: 34,
+        };
+
+        let glyph = normalize_glyph(&text_mask(&plate), &bbox, 32, 32);
+        assert_eq!(glyph.dimensions(), (32, 32));
+        assert!(glyph.get_pixel(16, 16)[0] > 200);
+        assert_eq!(glyph.get_pixel(0, 0)[0], 0);
+    }
+}
@@ -0,0 +1,167 @@
+use image::{imageops::FilterType, DynamicImage};
+use serde::{Deserialize, Serialize};
+
+use crate::preprocess::{PreprocessConfig, PreprocessStep, ThresholdMethod};
+use crate::{LicensePlateText, PlateValidity};
+
+/// Name reported for the first, unmodified attempt.
+pub const PRIMARY_STRATEGY: &str = "primary";
+
+/// One fallback attempt, expressed as changes to the base preprocessing
+/// pipeline and Tesseract settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrStrategy {
+    pub name: String,
+    /// Replaces the base pipeline entirely
+    #[serde(default)]
+    pub steps: Option<Vec<PreprocessStep>>,
+    /// Replaces every threshold step of the pipeline
+    #[serde(default)]
+    pub threshold: Option<ThresholdMethod>,
+    /// Inverts the preprocessed image unconditionally
+    #[serde(default)]
+    pub invert: bool,
+    /// Scales the crop by this factor before preprocessing
+    #[serde(default)]
+    pub upscale: Option<f32>,
+    /// Tesseract page segmentation mode for this attempt only, e.g. 7 for a
+    /// single text line; the engine's own mode is kept otherwise
+    #[serde(default)]
+    pub page_seg_mode: Option<u32>,
+}
+
+impl OcrStrategy {
+    fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            steps: None,
+            threshold: None,
+            invert: false,
+            upscale: None,
+            page_seg_mode: None,
+        }
+    }
+
+    /// Builds the preprocessing pipeline for this attempt from the base one.
+    pub fn preprocessing(&self, base: &PreprocessConfig) -> PreprocessConfig {
+        let mut steps = self.steps.clone().unwrap_or_else(|| base.steps.clone());
+
+        if let Some(method) = &self.threshold {
+            for step in steps.iter_mut() {
+                if let PreprocessStep::Threshold(current) = step {
+                    *current = method.clone();
+                }
+            }
+        }
+
+        if self.invert {
+            steps.push(PreprocessStep::Invert { only_if_dark: false });
+        }
+
+        PreprocessConfig { steps }
+    }
+
+    /// Applies the input-level changes (upscaling) to the plate crop.
+    pub fn prepare_input(&self, image: &DynamicImage) -> DynamicImage {
+        match self.upscale {
+            Some(factor) if factor > 0.0 && (factor - 1.0).abs() > f32::EPSILON => {
+                let width = ((image.width() as f32 * factor).round() as u32).max(1);
+                let height = ((image.height() as f32 * factor).round() as u32).max(1);
+                image.resize_exact(width, height, FilterType::Lanczos3)
+            }
+            _ => image.clone(),
+        }
+    }
+}
+
+/// Controls the fallback attempts made when the first read is not valid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Fallback strategies, tried in order
+    pub strategies: Vec<OcrStrategy>,
+    /// No new attempt is started once this much time has passed since the first one
+    pub time_budget_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            strategies: vec![
+                OcrStrategy {
+                    invert: true,
+                    ..OcrStrategy::named("inverted")
+                },
+                OcrStrategy {
+                    threshold: Some(ThresholdMethod::Otsu),
+                    ..OcrStrategy::named("otsu_threshold")
+                },
+                OcrStrategy {
+                    page_seg_mode: Some(7),
+                    ..OcrStrategy::named("single_line")
+                },
+                OcrStrategy {
+                    upscale: Some(2.0),
+                    ..OcrStrategy::named("upscaled")
+                },
+            ],
+            time_budget_ms: 300,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// No fallback attempts at all.
+    pub fn disabled() -> Self {
+        Self {
+            strategies: Vec::new(),
+            time_budget_ms: 0,
+        }
+    }
+}
+
+/// Ranks a read for choosing the best attempt: well-formed reads beat
+/// malformed ones, then higher confidence wins.
+pub(crate) fn rank(result: &LicensePlateText) -> (u8, f32) {
+    let class = match result.validity {
+        PlateValidity::Valid => 3,
+        PlateValidity::LowConfidence => 2,
+        PlateValidity::InvalidFormat => 1,
+        PlateValidity::Empty => 0,
+    };
+    (class, result.confidence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_override_and_invert() {
+        let strategy = OcrStrategy {
+            threshold: Some(ThresholdMethod::Otsu),
+            invert: true,
+            ..OcrStrategy::named("test")
+        };
+
+        let config = strategy.preprocessing(&PreprocessConfig::default());
+        assert!(config
+            .steps
+            .contains(&PreprocessStep::Threshold(ThresholdMethod::Otsu)));
+        assert_eq!(
+            config.steps.last(),
+            Some(&PreprocessStep::Invert { only_if_dark: false })
+        );
+        assert_eq!(config.steps.len(), PreprocessConfig::default().steps.len() + 1);
+    }
+
+    #[test]
+    fn test_upscale_input() {
+        let strategy = OcrStrategy {
+            upscale: Some(2.0),
+            ..OcrStrategy::named("upscaled")
+        };
+        let image = DynamicImage::new_luma8(40, 12);
+        let prepared = strategy.prepare_input(&image);
+        assert_eq!((prepared.width(), prepared.height()), (80, 24));
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, Rgb, RgbImage};
+use ndarray::{Array, CowArray};
+use ort::{Environment, ExecutionProvider, GraphOptimizationLevel, Session, SessionBuilder, Value};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::OcrError;
+
+/// Settings for the optional super-resolution stage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuperResolutionConfig {
+    pub model_path: PathBuf,
+    /// Crops narrower than this are upscaled
+    #[serde(default = "default_min_width")]
+    pub min_width: u32,
+    /// Crops shorter than this are upscaled
+    #[serde(default = "default_min_height")]
+    pub min_height: u32,
+}
+
+fn default_min_width() -> u32 {
+    100
+}
+
+fn default_min_height() -> u32 {
+    30
+}
+
+/// ONNX super-resolution model (e.g. an ESRGAN-lite export) run on the CPU.
+///
+/// The model takes a `1x3xHxW` RGB float tensor in `[0, 1]` and returns a
+/// `1x3x(sH)x(sW)` tensor in the same range; the scale factor is read from
+/// the output shape.
+pub struct SuperResolution {
+    session: Session,
+    min_width: u32,
+    min_height: u32,
+}
+
+impl SuperResolution {
+    pub fn new<P: AsRef<Path>>(model_path: P, min_width: u32, min_height: u32) -> Result<Self, OcrError> {
+        info!("Loading super-resolution model: {:?}", model_path.as_ref());
+
+        let environment = Environment::builder()
+            .with_name("YoloPlateSentry-super-resolution")
+            .with_execution_providers([ExecutionProvider::CPU(Default::default())])
+            .build()
+            .map_err(|e| OcrError::ModelLoadError(e.to_string()))?
+            .into_arc();
+
+        let session = SessionBuilder::new(&environment)
+            .and_then(|b| b.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|b| b.with_model_from_file(model_path))
+            .map_err(|e| OcrError::ModelLoadError(format!("Failed to load super-resolution model: {}", e)))?;
+
+        Ok(Self {
+            session,
+            min_width,
+            min_height,
+        })
+    }
+
+    pub fn from_config(config: &SuperResolutionConfig) -> Result<Self, OcrError> {
+        Self::new(&config.model_path, config.min_width, config.min_height)
+    }
+
+    /// Whether a crop is small enough to be worth upscaling.
+    pub fn applies_to(&self, image: &DynamicImage) -> bool {
+        image.width() < self.min_width || image.height() < self.min_height
+    }
+
+    pub fn upscale(&self, image: &DynamicImage) -> Result<DynamicImage, OcrError> {
+        let rgb = image.to_rgb8();
+        let (w, h) = (rgb.width() as usize, rgb.height() as usize);
+
+        let array = CowArray::from(
+            Array::from_shape_fn((1, 3, h, w), |(_, c, y, x)| {
+                rgb.get_pixel(x as u32, y as u32)[c] as f32 / 255.0
+            })
+            .into_dyn(),
+        );
+
+        let input = Value::from_array(self.session.allocator(), &array)
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        let outputs = self
+            .session
+            .run(vec![input])
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        let output = outputs[0]
+            .try_extract::<f32>()
+            .map_err(|e| OcrError::ProcessingError(e.to_string()))?;
+        let view = output.view();
+
+        let shape = view.shape();
+        if shape.len() != 4 || shape[1] != 3 {
+            return Err(OcrError::ProcessingError(format!(
+                "Unexpected super-resolution output shape {:?}",
+                shape
+            )));
+        }
+        let (out_h, out_w) = (shape[2], shape[3]);
+
+        let upscaled = RgbImage::from_fn(out_w as u32, out_h as u32, |x, y| {
+            let px = |c: usize| (view[[0, c, y as usize, x as usize]].clamp(0.0, 1.0) * 255.0).round() as u8;
+            Rgb([px(0), px(1), px(2)])
+        });
+
+        debug!("Super-resolution {}x{} -> {}x{}", w, h, out_w, out_h);
+        Ok(DynamicImage::ImageRgb8(upscaled))
+    }
+}
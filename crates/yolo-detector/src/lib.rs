@@ -8,6 +8,12 @@ use ort::{
 use thiserror::Error;
 use tracing::{debug, info};
 
+pub mod quality;
+pub mod tracker;
+
+pub use quality::{QualityConfig, QualityIssue, QualityReport};
+pub use tracker::{PlateTracker, TrackUpdate, TrackedPlate, TrackerConfig};
+
 #[derive(Debug, Error)]
 pub enum DetectorError {
     #[error("Failed to load YOLO model: {0}")]
@@ -19,6 +25,7 @@ pub enum DetectorError {
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BoundingBox {
     pub x_min: f32,
     pub y_min: f32,
@@ -27,6 +34,26 @@ pub struct BoundingBox {
     pub confidence: f32,
 }
 
+impl BoundingBox {
+    /// Intersection over union with another box.
+    pub fn iou(&self, other: &BoundingBox) -> f32 {
+        let x_left = self.x_min.max(other.x_min);
+        let y_top = self.y_min.max(other.y_min);
+        let x_right = self.x_max.min(other.x_max);
+        let y_bottom = self.y_max.min(other.y_max);
+
+        if x_right < x_left || y_bottom < y_top {
+            return 0.0;
+        }
+
+        let intersection_area = (x_right - x_left) * (y_bottom - y_top);
+        let self_area = (self.x_max - self.x_min) * (self.y_max - self.y_min);
+        let other_area = (other.x_max - other.x_min) * (other.y_max - other.y_min);
+
+        intersection_area / (self_area + other_area - intersection_area)
+    }
+}
+
 pub struct LicensePlateDetector {
     session: Session,
     input_name: String,
@@ -85,8 +112,18 @@ impl LicensePlateDetector {
             .map_err(|e| DetectorError::InferenceError(e.to_string()))?;
 
         // Post-process output
-        let boxes = self.postprocess_output(&outputs[0])?;
-        
+        let mut boxes = self.postprocess_output(&outputs[0])?;
+
+        // Map boxes from model input space back to the original frame
+        let scale_x = image.width() as f32 / INPUT_WIDTH as f32;
+        let scale_y = image.height() as f32 / INPUT_HEIGHT as f32;
+        for bbox in boxes.iter_mut() {
+            bbox.x_min = (bbox.x_min * scale_x).clamp(0.0, image.width() as f32);
+            bbox.x_max = (bbox.x_max * scale_x).clamp(0.0, image.width() as f32);
+            bbox.y_min = (bbox.y_min * scale_y).clamp(0.0, image.height() as f32);
+            bbox.y_max = (bbox.y_max * scale_y).clamp(0.0, image.height() as f32);
+        }
+
         debug!("Detected {} license plates", boxes.len());
         Ok(boxes)
     }
@@ -192,20 +229,7 @@ impl LicensePlateDetector {
     }
 
     fn calculate_iou(&self, box1: &BoundingBox, box2: &BoundingBox) -> f32 {
-        let x_left = box1.x_min.max(box2.x_min);
-        let y_top = box1.y_min.max(box2.y_min);
-        let x_right = box1.x_max.min(box2.x_max);
-        let y_bottom = box1.y_max.min(box2.y_max);
-
-        if x_right < x_left || y_bottom < y_top {
-            return 0.0;
-        }
-
-        let intersection_area = (x_right - x_left) * (y_bottom - y_top);
-        let box1_area = (box1.x_max - box1.x_min) * (box1.y_max - box1.y_min);
-        let box2_area = (box2.x_max - box2.x_min) * (box2.y_max - box2.y_min);
-        
-        intersection_area / (box1_area + box2_area - intersection_area)
+        box1.iou(box2)
     }
 }
 
@@ -222,4 +246,15 @@ mod tests {
     async fn test_license_plate_detection() {
         // TODO: Add tests with sample images
     }
+
+    #[test]
+    fn test_bounding_box_iou() {
+        let a = BoundingBox { x_min: 0.0, y_min: 0.0, x_max: 10.0, y_max: 10.0, confidence: 0.9 };
+        let b = BoundingBox { x_min: 5.0, y_min: 0.0, x_max: 15.0, y_max: 10.0, confidence: 0.8 };
+        let c = BoundingBox { x_min: 20.0, y_min: 20.0, x_max: 30.0, y_max: 30.0, confidence: 0.7 };
+
+        assert!((a.iou(&b) - 50.0 / 150.0).abs() < 1e-6);
+        assert_eq!(a.iou(&c), 0.0);
+        assert_eq!(a.iou(&a), 1.0);
+    }
 }
\ No newline at end of file
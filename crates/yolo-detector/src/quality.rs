@@ -0,0 +1,195 @@
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+
+use crate::BoundingBox;
+
+/// Thresholds a plate crop has to meet before it is worth running OCR on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    /// Minimum variance of the Laplacian; motion-blurred crops score low
+    pub min_sharpness: f32,
+    /// Mean brightness range, in `[0, 1]`
+    pub min_brightness: f32,
+    pub max_brightness: f32,
+    /// Maximum fraction of pixels clipped to pure black or white
+    pub max_clipped_fraction: f32,
+    /// Minimum crop size in frame pixels
+    pub min_width: u32,
+    pub min_height: u32,
+    /// Boxes within this many pixels of the frame border count as cut off
+    pub edge_margin: f32,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            min_sharpness: 100.0,
+            min_brightness: 0.15,
+            max_brightness: 0.9,
+            max_clipped_fraction: 0.4,
+            min_width: 60,
+            min_height: 15,
+            edge_margin: 2.0,
+        }
+    }
+}
+
+/// Reason a crop failed the quality gate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssue {
+    Blurry,
+    Underexposed,
+    Overexposed,
+    TooSmall,
+    /// The plate touches the frame edge, so characters may be missing
+    Truncated,
+}
+
+/// Quality measurements for one plate crop.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub sharpness: f32,
+    pub brightness: f32,
+    pub clipped_fraction: f32,
+    pub width: u32,
+    pub height: u32,
+    pub issues: Vec<QualityIssue>,
+    /// Overall score in `[0, 1]` for picking the best crop of a vehicle
+    pub score: f32,
+}
+
+impl QualityReport {
+    pub fn passes(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl QualityConfig {
+    /// Scores a plate crop cut from a frame of `frame_size` at `bbox`.
+    pub fn assess(&self, bbox: &BoundingBox, frame_size: (u32, u32), crop: &DynamicImage) -> QualityReport {
+        let gray = crop.to_luma8();
+        let (width, height) = gray.dimensions();
+        let sharpness = laplacian_variance(&gray);
+        let (brightness, clipped_fraction) = exposure(&gray);
+
+        let (frame_width, frame_height) = (frame_size.0 as f32, frame_size.1 as f32);
+        let truncated = bbox.x_min <= self.edge_margin
+            || bbox.y_min <= self.edge_margin
+            || bbox.x_max >= frame_width - self.edge_margin
+            || bbox.y_max >= frame_height - self.edge_margin;
+
+        let mut issues = Vec::new();
+        if sharpness < self.min_sharpness {
+            issues.push(QualityIssue::Blurry);
+        }
+        if brightness < self.min_brightness {
+            issues.push(QualityIssue::Underexposed);
+        }
+        if brightness > self.max_brightness || clipped_fraction > self.max_clipped_fraction {
+            issues.push(QualityIssue::Overexposed);
+        }
+        if width < self.min_width || height < self.min_height {
+            issues.push(QualityIssue::TooSmall);
+        }
+        if truncated {
+            issues.push(QualityIssue::Truncated);
+        }
+
+        // Each term saturates at twice its threshold so a huge crop can't
+        // make up for a blurry one
+        let sharpness_term = (sharpness / (2.0 * self.min_sharpness.max(1.0))).min(1.0);
+        let size_term = (width as f32 / (2.0 * self.min_width.max(1) as f32)).min(1.0);
+        let mid = (self.min_brightness + self.max_brightness) / 2.0;
+        let half_range = ((self.max_brightness - self.min_brightness) / 2.0).max(f32::EPSILON);
+        let exposure_term = (1.0 - (brightness - mid).abs() / half_range).clamp(0.0, 1.0)
+            * (1.0 - clipped_fraction);
+        let truncation_term = if truncated { 0.5 } else { 1.0 };
+        let score = sharpness_term * size_term * exposure_term.max(0.05) * truncation_term;
+
+        QualityReport {
+            sharpness,
+            brightness,
+            clipped_fraction,
+            width,
+            height,
+            issues,
+            score,
+        }
+    }
+}
+
+/// Variance of the 4-neighbour Laplacian, a standard focus measure.
+pub fn laplacian_variance(image: &GrayImage) -> f32 {
+    let (w, h) = image.dimensions();
+    if w < 3 || h < 3 {
+        return 0.0;
+    }
+
+    let px = |x: u32, y: u32| image.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let lap = px(x - 1, y) + px(x + 1, y) + px(x, y - 1) + px(x, y + 1) - 4.0 * px(x, y);
+            sum += lap;
+            sum_sq += lap * lap;
+        }
+    }
+
+    let n = ((w - 2) * (h - 2)) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean) as f32
+}
+
+/// Mean brightness in `[0, 1]` and the fraction of clipped pixels.
+fn exposure(image: &GrayImage) -> (f32, f32) {
+    let n = (image.width() * image.height()).max(1) as f32;
+    let (sum, clipped) = image.pixels().fold((0u64, 0u32), |(sum, clipped), p| {
+        (sum + p[0] as u64, clipped + u32::from(p[0] == 0 || p[0] == u8::MAX))
+    });
+    (sum as f32 / 255.0 / n, clipped as f32 / n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, RgbImage};
+
+    fn bbox(x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> BoundingBox {
+        BoundingBox { x_min, y_min, x_max, y_max, confidence: 0.9 }
+    }
+
+    fn stripes(width: u32, height: u32) -> DynamicImage {
+        let gray = GrayImage::from_fn(width, height, |x, _| {
+            if (x / 4) % 2 == 0 { Luma([40]) } else { Luma([200]) }
+        });
+        DynamicImage::ImageLuma8(gray)
+    }
+
+    #[test]
+    fn test_sharp_plate_passes_and_blurred_one_does_not() {
+        let config = QualityConfig::default();
+        let sharp = stripes(120, 40);
+        let report = config.assess(&bbox(100.0, 100.0, 220.0, 140.0), (640, 480), &sharp);
+        assert!(report.passes(), "{:?}", report.issues);
+
+        let blurred = DynamicImage::ImageLuma8(imageproc::filter::gaussian_blur_f32(&sharp.to_luma8(), 4.0));
+        let blurred_report = config.assess(&bbox(100.0, 100.0, 220.0, 140.0), (640, 480), &blurred);
+        assert!(blurred_report.issues.contains(&QualityIssue::Blurry));
+        assert!(blurred_report.score < report.score);
+    }
+
+    #[test]
+    fn test_small_dark_and_truncated_crops_are_flagged() {
+        let config = QualityConfig::default();
+        let dark = DynamicImage::ImageRgb8(RgbImage::new(40, 12));
+
+        let report = config.assess(&bbox(0.0, 300.0, 40.0, 312.0), (640, 480), &dark);
+        assert!(report.issues.contains(&QualityIssue::Underexposed));
+        assert!(report.issues.contains(&QualityIssue::TooSmall));
+        assert!(report.issues.contains(&QualityIssue::Truncated));
+        assert!(!report.passes());
+    }
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+
+use crate::BoundingBox;
+
+/// Settings for following plates across frames.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackerConfig {
+    /// Minimum IoU between a detection and a track's last box to continue the track
+    pub iou_threshold: f32,
+    /// A track ends after this many consecutive frames without a match
+    pub max_missed_frames: u32,
+    /// A track is ready for a decision once this many of its crops passed the quality checks
+    pub decide_after_frames: u32,
+    /// Minimum IoU with the previous box for the plate to count as standing still
+    pub stationary_iou: f32,
+    /// A track is also ready once its plate stood still for this many frames,
+    /// e.g. a car waiting at the barrier
+    pub stationary_frames: u32,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            iou_threshold: 0.3,
+            max_missed_frames: 5,
+            decide_after_frames: 3,
+            stationary_iou: 0.9,
+            stationary_frames: 2,
+        }
+    }
+}
+
+struct Track<T> {
+    id: u64,
+    bbox: BoundingBox,
+    missed: u32,
+    frames: u32,
+    /// Frames whose crop passed the quality checks
+    usable_frames: u32,
+    /// Consecutive frames the plate hasn't moved in
+    still_frames: u32,
+    best_score: f32,
+    best_usable: bool,
+    best: T,
+    decided: bool,
+}
+
+/// A tracked vehicle with its best observation so far.
+#[derive(Debug)]
+pub struct TrackedPlate<T> {
+    pub id: u64,
+    /// Number of frames the plate was seen in
+    pub frames: u32,
+    pub score: f32,
+    pub best: T,
+    /// Whether the track was already reported ready while in view
+    pub decided: bool,
+}
+
+/// What one frame changed.
+#[derive(Debug)]
+pub struct TrackUpdate<T> {
+    /// Vehicles that just became ready for a decision; each is reported
+    /// once, while it is still in view
+    pub ready: Vec<TrackedPlate<T>>,
+    /// Vehicles that left the scene
+    pub finished: Vec<TrackedPlate<T>>,
+}
+
+/// Greedy IoU tracker that keeps the best observation of every plate, so
+/// OCR and evidence images can use the best frame per vehicle. Crops that
+/// pass the quality checks beat higher-scoring ones that don't.
+pub struct PlateTracker<T> {
+    config: TrackerConfig,
+    tracks: Vec<Track<T>>,
+    next_id: u64,
+}
+
+impl<T> PlateTracker<T> {
+    pub fn new(config: TrackerConfig) -> Self {
+        Self {
+            config,
+            tracks: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Number of plates currently being followed.
+    pub fn active(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Ends every active track, e.g. when the camera stream stops.
+    pub fn flush(&mut self) -> Vec<TrackedPlate<T>> {
+        self.tracks.drain(..).map(Self::finish).collect()
+    }
+
+    fn finish(track: Track<T>) -> TrackedPlate<T> {
+        TrackedPlate {
+            id: track.id,
+            frames: track.frames,
+            score: track.best_score,
+            best: track.best,
+            decided: track.decided,
+        }
+    }
+
+    fn is_ready(&self, track: &Track<T>) -> bool {
+        !track.decided
+            && track.best_usable
+            && (track.usable_frames >= self.config.decide_after_frames
+                || track.still_frames >= self.config.stationary_frames)
+    }
+}
+
+impl<T: Clone> PlateTracker<T> {
+    /// Feeds one frame's detections, each with a quality score, whether its
+    /// crop passed the quality checks, and a payload. Reports the tracks
+    /// that became ready for a decision and those that ended with this frame.
+    pub fn update(&mut self, detections: Vec<(BoundingBox, f32, bool, T)>) -> TrackUpdate<T> {
+        let mut matched = vec![false; self.tracks.len()];
+
+        for (bbox, score, usable, payload) in detections {
+            let best_match = self
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !matched[*i])
+                .map(|(i, track)| (i, track.bbox.iou(&bbox)))
+                .filter(|(_, iou)| *iou >= self.config.iou_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            match best_match {
+                Some((i, iou)) => {
+                    matched[i] = true;
+                    let track = &mut self.tracks[i];
+                    track.bbox = bbox;
+                    track.missed = 0;
+                    track.frames += 1;
+                    track.usable_frames += usable as u32;
+                    track.still_frames = if iou >= self.config.stationary_iou {
+                        track.still_frames + 1
+                    } else {
+                        0
+                    };
+                    if (usable, score) > (track.best_usable, track.best_score) {
+                        track.best_score = score;
+                        track.best_usable = usable;
+                        track.best = payload;
+                    }
+                }
+                None => {
+                    self.tracks.push(Track {
+                        id: self.next_id,
+                        bbox,
+                        missed: 0,
+                        frames: 1,
+                        usable_frames: usable as u32,
+                        still_frames: 0,
+                        best_score: score,
+                        best_usable: usable,
+                        best: payload,
+                        decided: false,
+                    });
+                    self.next_id += 1;
+                    matched.push(true);
+                }
+            }
+        }
+
+        let mut ready = Vec::new();
+        for (i, is_matched) in matched.iter().enumerate() {
+            if !is_matched {
+                self.tracks[i].missed += 1;
+            } else if self.is_ready(&self.tracks[i]) {
+                let track = &mut self.tracks[i];
+                track.decided = true;
+                ready.push(TrackedPlate {
+                    id: track.id,
+                    frames: track.frames,
+                    score: track.best_score,
+                    best: track.best.clone(),
+                    decided: true,
+                });
+            }
+        }
+
+        let max_missed = self.config.max_missed_frames;
+        let (finished, active): (Vec<_>, Vec<_>) =
+            self.tracks.drain(..).partition(|track| track.missed > max_missed);
+        self.tracks = active;
+        TrackUpdate {
+            ready,
+            finished: finished.into_iter().map(Self::finish).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(x: f32) -> BoundingBox {
+        BoundingBox { x_min: x, y_min: 100.0, x_max: x + 100.0, y_max: 130.0, confidence: 0.9 }
+    }
+
+    #[test]
+    fn test_tracker_keeps_best_observation_per_vehicle() {
+        let mut tracker = PlateTracker::new(TrackerConfig {
+            max_missed_frames: 1,
+            ..TrackerConfig::default()
+        });
+
+        assert!(tracker.update(vec![(bbox(0.0), 0.4, true, "first")]).finished.is_empty());
+        assert!(tracker.update(vec![(bbox(10.0), 0.9, true, "sharp")]).finished.is_empty());
+        let update = tracker.update(vec![(bbox(20.0), 0.6, true, "later"), (bbox(400.0), 0.5, true, "other")]);
+        assert!(update.finished.is_empty());
+        assert_eq!(tracker.active(), 2);
+
+        // First vehicle leaves; it is reported once it has been missed for too long
+        assert!(tracker.update(vec![(bbox(400.0), 0.5, true, "other")]).finished.is_empty());
+        let finished = tracker.update(vec![(bbox(400.0), 0.5, true, "other")]).finished;
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].best, "sharp");
+        assert_eq!(finished[0].frames, 3);
+
+        let rest = tracker.flush();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].best, "other");
+        assert_eq!(tracker.active(), 0);
+    }
+
+    #[test]
+    fn test_stationary_vehicle_is_ready_while_in_view() {
+        let mut tracker = PlateTracker::new(TrackerConfig {
+            decide_after_frames: 10,
+            max_missed_frames: 1,
+            ..TrackerConfig::default()
+        });
+
+        // The car pulls up to the barrier and waits
+        assert!(tracker.update(vec![(bbox(0.0), 0.5, true, "arriving")]).ready.is_empty());
+        assert!(tracker.update(vec![(bbox(40.0), 0.6, true, "braking")]).ready.is_empty());
+        assert!(tracker.update(vec![(bbox(41.0), 0.7, true, "stopped")]).ready.is_empty());
+        let update = tracker.update(vec![(bbox(41.0), 0.6, true, "waiting")]);
+        assert!(update.finished.is_empty());
+        assert_eq!(update.ready.len(), 1);
+        assert_eq!(update.ready[0].best, "stopped");
+        assert_eq!(tracker.active(), 1);
+
+        // It is not reported again while it keeps waiting, nor when it leaves
+        for _ in 0..20 {
+            let update = tracker.update(vec![(bbox(41.0), 0.6, true, "waiting")]);
+            assert!(update.ready.is_empty() && update.finished.is_empty());
+        }
+        tracker.update(Vec::new());
+        let finished = tracker.update(Vec::new()).finished;
+        assert_eq!(finished.len(), 1);
+        assert!(finished[0].decided);
+    }
+
+    #[test]
+    fn test_moving_vehicle_is_ready_after_enough_usable_crops() {
+        let mut tracker = PlateTracker::new(TrackerConfig {
+            decide_after_frames: 2,
+            ..TrackerConfig::default()
+        });
+
+        // A blurry crop never wins over one that passes, whatever its score
+        assert!(tracker.update(vec![(bbox(0.0), 0.9, false, "blurry")]).ready.is_empty());
+        assert!(tracker.update(vec![(bbox(20.0), 0.5, true, "usable")]).ready.is_empty());
+        let ready = tracker.update(vec![(bbox(40.0), 0.4, true, "smaller")]).ready;
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].best, "usable");
+        assert_eq!(ready[0].frames, 3);
+    }
+}
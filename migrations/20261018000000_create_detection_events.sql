@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS detection_events (
+    id               INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Unix time in milliseconds, UTC
+    timestamp_ms     INTEGER NOT NULL,
+    camera_id        TEXT    NOT NULL,
+    plate_number     TEXT    NOT NULL,
+    raw_text         TEXT    NOT NULL,
+    processed_text   TEXT    NOT NULL,
+    confidence       REAL    NOT NULL,
+    bbox_x_min       REAL    NOT NULL,
+    bbox_y_min       REAL    NOT NULL,
+    bbox_x_max       REAL    NOT NULL,
+    bbox_y_max       REAL    NOT NULL,
+    bbox_confidence  REAL    NOT NULL,
+    access_status    TEXT    NOT NULL,
+    decision_reason  TEXT    NOT NULL,
+    read_status      TEXT    NOT NULL,
+    plate_type       TEXT    NOT NULL,
+    -- JSON array of per-character confidences
+    char_confidences TEXT    NOT NULL DEFAULT '[]',
+    image_path       TEXT    NOT NULL,
+    crop_path        TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_detection_events_timestamp ON detection_events (timestamp_ms);
+CREATE INDEX IF NOT EXISTS idx_detection_events_plate ON detection_events (plate_number);
+CREATE INDEX IF NOT EXISTS idx_detection_events_status ON detection_events (access_status, timestamp_ms);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS vehicles (
+    plate         TEXT PRIMARY KEY NOT NULL,
+    owner_name    TEXT NOT NULL DEFAULT '',
+    -- Unit, apartment or department the vehicle belongs to
+    unit          TEXT NOT NULL DEFAULT '',
+    access_level  TEXT NOT NULL DEFAULT 'guest',
+    -- Inclusive dates, YYYY-MM-DD; NULL means unbounded
+    valid_from    TEXT,
+    valid_until   TEXT,
+    -- JSON array of weekday names; empty means every day
+    weekdays      TEXT NOT NULL DEFAULT '[]',
+    -- JSON array of {"start": "HH:MM", "end": "HH:MM"}; empty means all day
+    time_ranges   TEXT NOT NULL DEFAULT '[]',
+    notes         TEXT NOT NULL DEFAULT ''
+);
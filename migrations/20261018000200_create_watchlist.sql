@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS watchlist (
+    plate     TEXT PRIMARY KEY NOT NULL,
+    -- stolen, banned or other
+    category  TEXT NOT NULL DEFAULT 'other',
+    reason    TEXT NOT NULL DEFAULT '',
+    notes     TEXT NOT NULL DEFAULT ''
+);
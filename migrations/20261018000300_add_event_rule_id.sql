@@ -0,0 +1,2 @@
+-- Id of the access policy rule that decided the event
+ALTER TABLE detection_events ADD COLUMN rule_id TEXT;
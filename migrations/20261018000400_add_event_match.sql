@@ -0,0 +1,3 @@
+-- Whether the plate matched the registry exactly or fuzzily, and how far off the read was
+ALTER TABLE detection_events ADD COLUMN match_kind TEXT;
+ALTER TABLE detection_events ADD COLUMN match_distance REAL;
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Unix time in milliseconds, UTC
+    timestamp_ms INTEGER NOT NULL,
+    -- User name or API key name
+    actor        TEXT    NOT NULL,
+    role         TEXT    NOT NULL,
+    action       TEXT    NOT NULL,
+    -- Plate the action applied to, empty if none
+    target       TEXT    NOT NULL DEFAULT '',
+    -- JSON, e.g. the list entry before and after the change
+    details      TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log (timestamp_ms);
+CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log (target, timestamp_ms);
@@ -0,0 +1,2 @@
+-- JSON array of what each gate did after the decision
+ALTER TABLE detection_events ADD COLUMN gate_results TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,3 @@
+-- 1 if the row comes from the whitelist file and follows it on startup and
+-- reload, 0 if it was added through the API or the command line
+ALTER TABLE vehicles ADD COLUMN from_whitelist_file INTEGER NOT NULL DEFAULT 0;
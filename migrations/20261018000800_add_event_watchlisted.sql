@@ -0,0 +1,2 @@
+-- 1 if the plate or one of its alternative readings was on the watchlist
+ALTER TABLE detection_events ADD COLUMN watchlisted INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Read statuses are stored in snake_case like the other event enums
+UPDATE detection_events SET read_status = CASE read_status
+    WHEN 'Valid' THEN 'valid'
+    WHEN 'InvalidFormat' THEN 'invalid_format'
+    WHEN 'Empty' THEN 'empty'
+    WHEN 'LowConfidence' THEN 'low_confidence'
+    ELSE read_status
+END;
@@ -0,0 +1,243 @@
+//! Audit log of changes made through the API, the command line or the
+//! config file: who changed which list entry, opened which gate or reloaded
+//! the configuration, and when.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::{Caller, Role};
+use crate::db::{from_text, to_text};
+use crate::live::{ListChange, PlateListKind};
+use crate::registry::{Vehicle, WhitelistDiff};
+use crate::store::DEFAULT_QUERY_LIMIT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    WhitelistAdded,
+    WhitelistChanged,
+    WhitelistRemoved,
+    WatchlistAdded,
+    WatchlistChanged,
+    WatchlistRemoved,
+    /// Opened by hand; gates opened for allowed plates are on their events
+    GateOpened,
+    ConfigReloaded,
+}
+
+impl AuditAction {
+    pub fn list(list: PlateListKind, change: ListChange) -> Self {
+        match (list, change) {
+            (PlateListKind::Whitelist, ListChange::Added) => AuditAction::WhitelistAdded,
+            (PlateListKind::Whitelist, ListChange::Changed) => AuditAction::WhitelistChanged,
+            (PlateListKind::Whitelist, ListChange::Removed) => AuditAction::WhitelistRemoved,
+            (PlateListKind::Watchlist, ListChange::Added) => AuditAction::WatchlistAdded,
+            (PlateListKind::Watchlist, ListChange::Changed) => AuditAction::WatchlistChanged,
+            (PlateListKind::Watchlist, ListChange::Removed) => AuditAction::WatchlistRemoved,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// User name or API key name
+    pub actor: String,
+    pub role: Role,
+    pub action: AuditAction,
+    /// Plate or camera id the action applied to, empty if none
+    pub target: String,
+    /// The list entry `before` and `after` the change, or the gate results
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+}
+
+/// Filter for `AuditLog::query`; unset fields match everything.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub target: Option<String>,
+    /// Inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Repository for audit entries. Cheap to clone; clones share the pool.
+#[derive(Clone)]
+pub struct AuditLog {
+    pool: SqlitePool,
+}
+
+impl AuditLog {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        caller: &Caller,
+        action: AuditAction,
+        target: &str,
+        details: Option<serde_json::Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO audit_log (timestamp_ms, actor, role, action, target, details) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Utc::now().timestamp_millis())
+        .bind(&caller.name)
+        .bind(to_text(&caller.role))
+        .bind(to_text(&action))
+        .bind(target)
+        .bind(details.map(|details| details.to_string()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Records every entry of a whitelist diff; `old` holds the registrations
+    /// it was computed from.
+    pub async fn record_whitelist_diff(
+        &self,
+        caller: &Caller,
+        old: &[Vehicle],
+        diff: &WhitelistDiff,
+    ) -> Result<(), sqlx::Error> {
+        let before = |plate: &str| old.iter().find(|v| v.plate == plate);
+        let changes = diff
+            .added
+            .iter()
+            .map(|v| (v.plate.as_str(), ListChange::Added, Some(v)))
+            .chain(diff.changed.iter().map(|v| (v.plate.as_str(), ListChange::Changed, Some(v))))
+            .chain(diff.removed.iter().map(|plate| (plate.as_str(), ListChange::Removed, None)));
+        for (plate, change, after) in changes {
+            let details = serde_json::json!({ "before": before(plate), "after": after });
+            let action = AuditAction::list(PlateListKind::Whitelist, change);
+            self.record(caller, action, plate, Some(details)).await?;
+        }
+        Ok(())
+    }
+
+    /// Matching entries, newest first.
+    pub async fn query(&self, filter: &AuditQuery) -> Result<Vec<AuditEntry>, sqlx::Error> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM audit_log WHERE 1 = 1");
+        if let Some(actor) = &filter.actor {
+            builder.push(" AND actor = ").push_bind(actor.clone());
+        }
+        if let Some(target) = &filter.target {
+            builder.push(" AND target = ").push_bind(target.clone());
+        }
+        if let Some(from) = filter.from {
+            builder.push(" AND timestamp_ms >= ").push_bind(from.timestamp_millis());
+        }
+        if let Some(to) = filter.to {
+            builder.push(" AND timestamp_ms < ").push_bind(to.timestamp_millis());
+        }
+        builder
+            .push(" ORDER BY timestamp_ms DESC, id DESC LIMIT ")
+            .push_bind(filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT) as i64)
+            .push(" OFFSET ")
+            .push_bind(filter.offset.unwrap_or(0) as i64);
+
+        builder
+            .build()
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+}
+
+fn from_row(row: &SqliteRow) -> Result<AuditEntry, sqlx::Error> {
+    let timestamp_ms: i64 = row.try_get("timestamp_ms")?;
+    let timestamp = Utc
+        .timestamp_millis_opt(timestamp_ms)
+        .single()
+        .ok_or_else(|| sqlx::Error::Decode(format!("Invalid timestamp {}", timestamp_ms).into()))?;
+    let details: Option<String> = row.try_get("details")?;
+
+    Ok(AuditEntry {
+        id: row.try_get("id")?,
+        timestamp,
+        actor: row.try_get("actor")?,
+        role: from_text(&row.try_get::<String, _>("role")?)?,
+        action: from_text(&row.try_get::<String, _>("action")?)?,
+        target: row.try_get("target")?,
+        details: details
+            .map(|details| serde_json::from_str(&details))
+            .transpose()
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_query() {
+        let pool = crate::db::connect("sqlite::memory:").await.unwrap();
+        let audit = AuditLog::new(pool);
+        let guard = Caller { name: "somchai".into(), role: Role::Guard };
+        let admin = Caller { name: "admin".into(), role: Role::Admin };
+
+        let vehicle = serde_json::json!({"plate": "AB1234", "access_level": "guest"});
+        let added = AuditAction::list(PlateListKind::Whitelist, ListChange::Added);
+        audit.record(&guard, added, "AB1234", Some(vehicle.clone())).await.unwrap();
+        audit.record(&guard, AuditAction::ConfigReloaded, "", None).await.unwrap();
+        audit.record(&admin, AuditAction::WhitelistRemoved, "AB1234", None).await.unwrap();
+
+        let entries = audit.query(&AuditQuery::default()).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].action, AuditAction::WhitelistRemoved);
+
+        let filter = AuditQuery {
+            actor: Some("somchai".into()),
+            target: Some("AB1234".into()),
+            ..Default::default()
+        };
+        let entries = audit.query(&filter).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, AuditAction::WhitelistAdded);
+        assert_eq!(entries[0].role, Role::Guard);
+        assert_eq!(entries[0].details, Some(vehicle));
+    }
+
+    #[tokio::test]
+    async fn test_record_whitelist_diff() {
+        let pool = crate::db::connect("sqlite::memory:").await.unwrap();
+        let audit = AuditLog::new(pool);
+        let file = Caller::local("config-file");
+
+        let old = vec![Vehicle::new("AB1234"), Vehicle::new("KK5555")];
+        let mut changed = Vehicle::new("AB1234");
+        changed.notes = "Visitor".into();
+        let new = vec![changed, Vehicle::new("ZZ9999")];
+        audit.record_whitelist_diff(&file, &old, &WhitelistDiff::between(&old, &new)).await.unwrap();
+
+        let mut entries = audit.query(&AuditQuery::default()).await.unwrap();
+        entries.sort_by(|a, b| a.target.cmp(&b.target));
+        let actions: Vec<_> = entries.iter().map(|e| (e.target.as_str(), e.action)).collect();
+        assert_eq!(
+            actions,
+            [
+                ("AB1234", AuditAction::WhitelistChanged),
+                ("KK5555", AuditAction::WhitelistRemoved),
+                ("ZZ9999", AuditAction::WhitelistAdded),
+            ]
+        );
+        assert!(entries.iter().all(|e| e.actor == "config-file" && e.role == Role::Admin));
+        let details = entries[0].details.as_ref().unwrap();
+        assert_eq!(details["before"]["notes"], "");
+        assert_eq!(details["after"]["notes"], "Visitor");
+        assert!(entries[1].details.as_ref().unwrap()["after"].is_null());
+    }
+}
@@ -0,0 +1,353 @@
+//! API authentication: API keys for other systems, and username/password
+//! logins that get a signed session token (JWT). Either one carries a role
+//! that decides which endpoints may be used.
+
+use std::future::{ready, Ready};
+use std::sync::LazyLock;
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::api::{ApiError, ApiState};
+use crate::configuration::Secret;
+use crate::db::to_text;
+
+/// What a caller may do; each role may also do everything the ones below it may.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Read events, lists and camera status, and follow the live stream
+    Viewer,
+    /// Also edit the whitelist and open gates
+    Guard,
+    /// Also edit the watchlist, read and reload the configuration and read the audit log
+    Admin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Signs session tokens; changing it logs everybody out
+    pub jwt_secret: Secret,
+    /// How long a login stays valid
+    #[serde(default = "default_session_hours")]
+    pub session_hours: u32,
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+}
+
+fn default_session_hours() -> u32 {
+    12
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserConfig {
+    pub username: String,
+    /// Argon2 hash as printed by the `hash-password` command
+    pub password_hash: Secret,
+    pub role: Role,
+}
+
+/// A key sent in the `X-API-Key` header.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Recorded in the audit log as the actor
+    pub name: String,
+    pub key: Secret,
+    pub role: Role,
+}
+
+impl AuthConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.jwt_secret.expose().len() < 32 {
+            problems.push("api.auth.jwt_secret must be at least 32 characters".to_string());
+        }
+        if self.session_hours == 0 {
+            problems.push("api.auth.session_hours must be positive".to_string());
+        }
+        for (i, user) in self.users.iter().enumerate() {
+            if user.username.trim().is_empty() {
+                problems.push(format!("api.auth.users[{}].username must not be empty", i));
+            }
+            if self.users[..i].iter().any(|other| other.username == user.username) {
+                problems.push(format!("api.auth.users: duplicate username '{}'", user.username));
+            }
+            if let Err(e) = PasswordHash::new(user.password_hash.expose()) {
+                problems.push(format!("api.auth.users[{}].password_hash is not a password hash: {}", i, e));
+            }
+        }
+        for (i, key) in self.api_keys.iter().enumerate() {
+            if key.key.expose().len() < 16 {
+                problems.push(format!("api.auth.api_keys[{}].key must be at least 16 characters", i));
+            }
+            if self.api_keys[..i].iter().any(|other| other.name == key.name) {
+                problems.push(format!("api.auth.api_keys: duplicate name '{}'", key.name));
+            }
+        }
+        problems
+    }
+}
+
+/// Checked against for unknown usernames, so a failed login takes as long
+/// whether or not the user exists.
+static UNKNOWN_USER_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("unknown user").expect("hashing a fixed password"));
+
+/// Hashes a password for `api.auth.users`.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+/// Who is making a request.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Caller {
+    /// User name or API key name
+    pub name: String,
+    pub role: Role,
+}
+
+impl Caller {
+    /// Everybody is an admin when authentication is not configured.
+    fn anonymous() -> Self {
+        Self {
+            name: "anonymous".to_string(),
+            role: Role::Admin,
+        }
+    }
+
+    /// Changes made outside the API, from the config file or the command
+    /// line, by someone who can already edit everything.
+    pub fn local(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            role: Role::Admin,
+        }
+    }
+
+    pub fn require(&self, role: Role) -> Result<(), ApiError> {
+        if self.role < role {
+            return Err(ApiError::Forbidden(format!("This requires the {} role", to_text(&role))));
+        }
+        Ok(())
+    }
+}
+
+/// A successful login.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Session {
+    /// Send as `Authorization: Bearer <token>`
+    pub token: String,
+    pub username: String,
+    pub role: Role,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    role: Role,
+    iat: i64,
+    exp: i64,
+}
+
+/// Checks credentials against `AuthConfig`, built once when the API starts.
+pub struct Authenticator {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    session: Duration,
+    users: Vec<UserConfig>,
+    api_keys: Vec<ApiKeyConfig>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Self {
+        let secret = config.jwt_secret.expose().as_bytes();
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            session: Duration::hours(config.session_hours as i64),
+            users: config.users.clone(),
+            api_keys: config.api_keys.clone(),
+        }
+    }
+
+    /// A session for the user, or `None` if the name or password is wrong.
+    pub fn login(&self, username: &str, password: &str) -> Option<Session> {
+        let user = self.users.iter().find(|user| user.username == username);
+        let hash = user.map_or(UNKNOWN_USER_HASH.as_str(), |user| user.password_hash.expose());
+        let verified = PasswordHash::new(hash)
+            .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok());
+        let user = user.filter(|_| verified)?;
+
+        let now = Utc::now();
+        let expires_at = now + self.session;
+        let claims = Claims {
+            sub: user.username.clone(),
+            role: user.role,
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+        };
+        let token = jsonwebtoken::encode(&Header::default(), &claims, &self.encoding).ok()?;
+        Some(Session {
+            token,
+            username: user.username.clone(),
+            role: user.role,
+            expires_at,
+        })
+    }
+
+    /// The caller a session token was issued to, if it is genuine, unexpired
+    /// and the user is still configured. The role is the configured one, not
+    /// the one at login, so demoting or removing a user takes effect at once.
+    pub fn verify_token(&self, token: &str) -> Option<Caller> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .ok()?
+            .claims;
+        let user = self.users.iter().find(|user| user.username == claims.sub)?;
+        Some(Caller {
+            name: claims.sub,
+            role: user.role,
+        })
+    }
+
+    pub fn verify_api_key(&self, key: &str) -> Option<Caller> {
+        self.api_keys
+            .iter()
+            .find(|candidate| constant_time_eq(candidate.key.expose().as_bytes(), key.as_bytes()))
+            .map(|candidate| Caller {
+                name: candidate.name.clone(),
+                role: candidate.role,
+            })
+    }
+}
+
+/// Compares without stopping at the first difference, so response times
+/// don't give away how much of a key was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    access_token: Option<String>,
+}
+
+/// Credentials are taken from the `X-API-Key` header, an
+/// `Authorization: Bearer` session token, or an `access_token` query
+/// parameter for browsers opening WebSockets and images, in that order.
+fn authenticate(request: &HttpRequest) -> Result<Caller, ApiError> {
+    let state = request
+        .app_data::<web::Data<ApiState>>()
+        .ok_or_else(|| ApiError::Unavailable("API state is missing".into()))?;
+    let settings = state.settings.load();
+    let Some(auth) = &settings.auth else {
+        return Ok(Caller::anonymous());
+    };
+    let invalid = || ApiError::Unauthorized("Invalid or expired credentials".into());
+
+    let header = |name| request.headers().get(name).and_then(|value| value.to_str().ok());
+    if let Some(key) = header("X-API-Key") {
+        return auth.verify_api_key(key).ok_or_else(invalid);
+    }
+    let token = match header("Authorization") {
+        Some(value) => Some(value.strip_prefix("Bearer ").ok_or_else(invalid)?.to_string()),
+        None => web::Query::<TokenQuery>::from_query(request.query_string())
+            .ok()
+            .and_then(|query| query.into_inner().access_token),
+    };
+    match token {
+        Some(token) => auth.verify_token(&token).ok_or_else(invalid),
+        None => Err(ApiError::Unauthorized("Log in or send an API key".into())),
+    }
+}
+
+impl FromRequest for Caller {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, ApiError>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AuthConfig {
+        serde_json::from_value(serde_json::json!({
+            "jwt_secret": "0123456789abcdef0123456789abcdef",
+            "users": [{"username": "somchai", "password_hash": hash_password("gatehouse").unwrap(), "role": "guard"}],
+            "api_keys": [{"name": "kiosk", "key": "kiosk-key-0123456789", "role": "viewer"}]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_login_and_tokens() {
+        let config = config();
+        assert!(config.validate().is_empty());
+        let auth = Authenticator::new(&config);
+
+        assert!(auth.login("somchai", "wrong").is_none());
+        assert!(auth.login("nobody", "gatehouse").is_none());
+        let session = auth.login("somchai", "gatehouse").unwrap();
+        assert_eq!(session.role, Role::Guard);
+        assert!(session.expires_at > Utc::now() + Duration::hours(11));
+
+        let caller = auth.verify_token(&session.token).unwrap();
+        assert_eq!(caller, Caller { name: "somchai".into(), role: Role::Guard });
+        assert!(auth.verify_token(&format!("{}x", session.token)).is_none());
+
+        let other = AuthConfig {
+            jwt_secret: serde_json::from_str("\"another-secret-another-secret-another\"").unwrap(),
+            ..config
+        };
+        assert!(Authenticator::new(&other).verify_token(&session.token).is_none());
+    }
+
+    #[test]
+    fn test_tokens_follow_the_configured_users() {
+        let mut config = config();
+        let session = Authenticator::new(&config).login("somchai", "gatehouse").unwrap();
+
+        config.users[0].role = Role::Viewer;
+        let caller = Authenticator::new(&config).verify_token(&session.token).unwrap();
+        assert_eq!(caller.role, Role::Viewer);
+
+        config.users.clear();
+        assert!(Authenticator::new(&config).verify_token(&session.token).is_none());
+    }
+
+    #[test]
+    fn test_api_keys_and_roles() {
+        let auth = Authenticator::new(&config());
+        let caller = auth.verify_api_key("kiosk-key-0123456789").unwrap();
+        assert_eq!(caller.name, "kiosk");
+        assert!(auth.verify_api_key("kiosk-key-012345678").is_none());
+
+        assert!(caller.require(Role::Viewer).is_ok());
+        assert!(matches!(caller.require(Role::Guard), Err(ApiError::Forbidden(_))));
+        assert!(Caller::anonymous().require(Role::Admin).is_ok());
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut config = config();
+        config.users.push(config.users[0].clone());
+        config.users[1].password_hash = serde_json::from_str("\"plain-text\"").unwrap();
+        config.session_hours = 0;
+        let problems = config.validate();
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.contains("duplicate username 'somchai'")));
+    }
+}
@@ -0,0 +1,506 @@
+//! End-to-end benchmark of detection + OCR over labeled full frames.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use plate_ocr::PlateOcr;
+use yolo_detector::{BoundingBox, LicensePlateDetector};
+
+/// IoU at which a detection counts as a true positive for precision/recall.
+const MATCH_IOU: f32 = 0.5;
+
+/// A labeled plate in a frame, in pixel coordinates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabeledPlate {
+    pub x_min: f32,
+    pub y_min: f32,
+    pub x_max: f32,
+    pub y_max: f32,
+    pub plate: Option<String>,
+}
+
+impl LabeledPlate {
+    fn as_box(&self) -> BoundingBox {
+        BoundingBox {
+            x_min: self.x_min,
+            y_min: self.y_min,
+            x_max: self.x_max,
+            y_max: self.y_max,
+            confidence: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledFrame {
+    pub image: PathBuf,
+    pub plates: Vec<LabeledPlate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationFormat {
+    /// One `<image stem>.txt` per image with `class cx cy w h [plate]` rows, normalized to [0, 1]
+    Yolo,
+    /// A single COCO JSON file; an optional `plate` field on annotations holds the text
+    Coco,
+}
+
+impl std::str::FromStr for AnnotationFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yolo" => Ok(AnnotationFormat::Yolo),
+            "coco" => Ok(AnnotationFormat::Coco),
+            other => Err(format!("Unknown annotation format '{}'", other)),
+        }
+    }
+}
+
+/// Loads labeled frames. For YOLO, `annotations` is the label directory
+/// (often the image directory itself); for COCO it is the JSON file.
+pub fn load_annotations(
+    format: AnnotationFormat,
+    image_dir: &Path,
+    annotations: &Path,
+) -> Result<Vec<LabeledFrame>, Box<dyn Error>> {
+    match format {
+        AnnotationFormat::Yolo => load_yolo(image_dir, annotations),
+        AnnotationFormat::Coco => load_coco(image_dir, annotations),
+    }
+}
+
+/// JPEG, PNG and BMP files directly in `dir`, sorted by name.
+pub fn list_images(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut images: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "jpg" | "jpeg" | "png" | "bmp"))
+                .unwrap_or(false)
+        })
+        .collect();
+    images.sort();
+    Ok(images)
+}
+
+fn load_yolo(image_dir: &Path, label_dir: &Path) -> Result<Vec<LabeledFrame>, Box<dyn Error>> {
+    let mut frames = Vec::new();
+
+    for image in list_images(image_dir)? {
+        let stem = image.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let label_path = label_dir.join(format!("{}.txt", stem));
+        // Images without a label file are negatives
+        let content = std::fs::read_to_string(&label_path).unwrap_or_default();
+
+        let (width, height) = image::image_dimensions(&image)?;
+        let plates = parse_yolo_labels(&content, width as f32, height as f32);
+        frames.push(LabeledFrame { image, plates });
+    }
+
+    Ok(frames)
+}
+
+fn parse_yolo_labels(content: &str, width: f32, height: f32) -> Vec<LabeledPlate> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 {
+                return None;
+            }
+            let values: Vec<f32> = fields[1..5].iter().filter_map(|v| v.parse().ok()).collect();
+            if values.len() != 4 {
+                return None;
+            }
+            let (cx, cy, w, h) = (values[0] * width, values[1] * height, values[2] * width, values[3] * height);
+            Some(LabeledPlate {
+                x_min: cx - w / 2.0,
+                y_min: cy - h / 2.0,
+                x_max: cx + w / 2.0,
+                y_max: cy + h / 2.0,
+                plate: fields.get(5).map(|p| p.to_uppercase()),
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct CocoFile {
+    images: Vec<CocoImage>,
+    #[serde(default)]
+    annotations: Vec<CocoAnnotation>,
+}
+
+#[derive(Deserialize)]
+struct CocoImage {
+    id: u64,
+    file_name: String,
+}
+
+#[derive(Deserialize)]
+struct CocoAnnotation {
+    image_id: u64,
+    bbox: [f32; 4],
+    #[serde(default)]
+    plate: Option<String>,
+}
+
+fn load_coco(image_dir: &Path, path: &Path) -> Result<Vec<LabeledFrame>, Box<dyn Error>> {
+    let coco: CocoFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+    let mut plates: HashMap<u64, Vec<LabeledPlate>> = HashMap::new();
+    for annotation in coco.annotations {
+        let [x, y, w, h] = annotation.bbox;
+        plates.entry(annotation.image_id).or_default().push(LabeledPlate {
+            x_min: x,
+            y_min: y,
+            x_max: x + w,
+            y_max: y + h,
+            plate: annotation.plate.map(|p| p.to_uppercase()),
+        });
+    }
+
+    Ok(coco
+        .images
+        .into_iter()
+        .map(|image| LabeledFrame {
+            image: image_dir.join(&image.file_name),
+            plates: plates.remove(&image.id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * samples.len() as f64).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1]
+        };
+        Self {
+            samples: samples.len(),
+            mean_ms: samples.iter().sum::<f64>() / samples.len() as f64,
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms: *samples.last().unwrap_or(&0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetectionMetrics {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub precision: f64,
+    pub recall: f64,
+    /// Average precision at IoU 0.5
+    pub map_50: f64,
+    /// Average precision averaged over IoU 0.5 to 0.95 in steps of 0.05
+    pub map_50_95: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EndToEndMetrics {
+    /// Labeled plates that have a ground-truth text
+    pub labeled_plates: usize,
+    /// Of those, plates that were detected and read exactly
+    pub correct_reads: usize,
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub frames: usize,
+    pub detection: DetectionMetrics,
+    pub end_to_end: EndToEndMetrics,
+    pub detection_latency: LatencyStats,
+    pub ocr_latency: LatencyStats,
+    pub frame_latency: LatencyStats,
+}
+
+/// Per-frame detector output kept for metric computation.
+struct FrameResult {
+    detections: Vec<BoundingBox>,
+    labels: Vec<LabeledPlate>,
+    /// Text read for each detection, same order as `detections`
+    reads: Vec<Option<String>>,
+}
+
+/// Replays labeled frames through the detector and OCR.
+pub async fn run(
+    detector: &LicensePlateDetector,
+    ocr: &PlateOcr,
+    frames: &[LabeledFrame],
+) -> Result<BenchReport, Box<dyn Error>> {
+    let mut results = Vec::with_capacity(frames.len());
+    let (mut detection_ms, mut ocr_ms, mut frame_ms) = (Vec::new(), Vec::new(), Vec::new());
+
+    for frame in frames {
+        let image = match image::open(&frame.image) {
+            Ok(image) => image,
+            Err(e) => {
+                warn!("Skipping {:?}: {}", frame.image, e);
+                continue;
+            }
+        };
+
+        let frame_started = Instant::now();
+        let detections = detector.detect_license_plate(&image).await?;
+        detection_ms.push(frame_started.elapsed().as_secs_f64() * 1000.0);
+
+        let mut reads = Vec::with_capacity(detections.len());
+        for bbox in &detections {
+            let started = Instant::now();
+            let crop = image.crop_imm(
+                bbox.x_min.max(0.0) as u32,
+                bbox.y_min.max(0.0) as u32,
+                (bbox.x_max - bbox.x_min).max(1.0) as u32,
+                (bbox.y_max - bbox.y_min).max(1.0) as u32,
+            );
+            let read = ocr.process_plate(&crop).ok().map(|r| r.processed_text);
+            ocr_ms.push(started.elapsed().as_secs_f64() * 1000.0);
+            reads.push(read);
+        }
+        frame_ms.push(frame_started.elapsed().as_secs_f64() * 1000.0);
+
+        results.push(FrameResult {
+            detections,
+            labels: frame.plates.clone(),
+            reads,
+        });
+    }
+
+    let report = BenchReport {
+        frames: results.len(),
+        detection: detection_metrics(&results),
+        end_to_end: end_to_end_metrics(&results),
+        detection_latency: LatencyStats::from_samples(detection_ms),
+        ocr_latency: LatencyStats::from_samples(ocr_ms),
+        frame_latency: LatencyStats::from_samples(frame_ms),
+    };
+
+    info!(
+        "Benchmark: {} frames, precision {:.3}, recall {:.3}, mAP@0.5 {:.3}, plate accuracy {:.2}%",
+        report.frames,
+        report.detection.precision,
+        report.detection.recall,
+        report.detection.map_50,
+        report.end_to_end.accuracy * 100.0
+    );
+
+    Ok(report)
+}
+
+/// Greedily matches detections (highest confidence first) to labels at `iou`.
+/// Returns, per detection, the index of the matched label.
+fn match_detections(detections: &[BoundingBox], labels: &[LabeledPlate], iou: f32) -> Vec<Option<usize>> {
+    let mut order: Vec<usize> = (0..detections.len()).collect();
+    order.sort_by(|a, b| {
+        detections[*b]
+            .confidence
+            .partial_cmp(&detections[*a].confidence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut taken = vec![false; labels.len()];
+    let mut matches = vec![None; detections.len()];
+    for i in order {
+        let best = labels
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| !taken[*j])
+            .map(|(j, label)| (j, detections[i].iou(&label.as_box())))
+            .filter(|(_, overlap)| *overlap >= iou)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        if let Some((j, _)) = best {
+            taken[j] = true;
+            matches[i] = Some(j);
+        }
+    }
+    matches
+}
+
+fn detection_metrics(results: &[FrameResult]) -> DetectionMetrics {
+    let mut metrics = DetectionMetrics::default();
+    for result in results {
+        let matches = match_detections(&result.detections, &result.labels, MATCH_IOU);
+        let tp = matches.iter().filter(|m| m.is_some()).count();
+        metrics.true_positives += tp;
+        metrics.false_positives += matches.len() - tp;
+        metrics.false_negatives += result.labels.len() - tp;
+    }
+
+    let predicted = metrics.true_positives + metrics.false_positives;
+    let labeled = metrics.true_positives + metrics.false_negatives;
+    metrics.precision = if predicted > 0 { metrics.true_positives as f64 / predicted as f64 } else { 0.0 };
+    metrics.recall = if labeled > 0 { metrics.true_positives as f64 / labeled as f64 } else { 0.0 };
+
+    metrics.map_50 = average_precision(results, 0.5);
+    let thresholds: Vec<f32> = (0..10).map(|i| 0.5 + i as f32 * 0.05).collect();
+    metrics.map_50_95 = thresholds
+        .iter()
+        .map(|t| average_precision(results, *t))
+        .sum::<f64>()
+        / thresholds.len() as f64;
+    metrics
+}
+
+/// All-point interpolated average precision for the single plate class.
+fn average_precision(results: &[FrameResult], iou: f32) -> f64 {
+    let total_labels: usize = results.iter().map(|r| r.labels.len()).sum();
+    if total_labels == 0 {
+        return 0.0;
+    }
+
+    let mut scored: Vec<(f32, bool)> = results
+        .iter()
+        .flat_map(|r| {
+            match_detections(&r.detections, &r.labels, iou)
+                .into_iter()
+                .zip(&r.detections)
+                .map(|(m, d)| (d.confidence, m.is_some()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut curve = Vec::with_capacity(scored.len());
+    let (mut tp, mut fp) = (0usize, 0usize);
+    for (_, is_tp) in &scored {
+        if *is_tp {
+            tp += 1;
+        } else {
+            fp += 1;
+        }
+        curve.push((tp as f64 / total_labels as f64, tp as f64 / (tp + fp) as f64));
+    }
+
+    // Make precision monotonically decreasing, then integrate over recall
+    for i in (0..curve.len().saturating_sub(1)).rev() {
+        curve[i].1 = curve[i].1.max(curve[i + 1].1);
+    }
+    let mut ap = 0.0;
+    let mut previous_recall = 0.0;
+    for (recall, precision) in curve {
+        ap += (recall - previous_recall) * precision;
+        previous_recall = recall;
+    }
+    ap
+}
+
+fn end_to_end_metrics(results: &[FrameResult]) -> EndToEndMetrics {
+    let mut metrics = EndToEndMetrics::default();
+    for result in results {
+        let matches = match_detections(&result.detections, &result.labels, MATCH_IOU);
+        metrics.labeled_plates += result.labels.iter().filter(|l| l.plate.is_some()).count();
+        for (detection, matched) in matches.iter().enumerate() {
+            let Some(label) = matched.map(|j| &result.labels[j]) else {
+                continue;
+            };
+            if label.plate.is_some() && result.reads[detection] == label.plate {
+                metrics.correct_reads += 1;
+            }
+        }
+    }
+    metrics.accuracy = if metrics.labeled_plates > 0 {
+        metrics.correct_reads as f64 / metrics.labeled_plates as f64
+    } else {
+        0.0
+    };
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(x: f32, plate: &str) -> LabeledPlate {
+        LabeledPlate {
+            x_min: x,
+            y_min: 0.0,
+            x_max: x + 100.0,
+            y_max: 40.0,
+            plate: Some(plate.into()),
+        }
+    }
+
+    fn detection(x: f32, confidence: f32) -> BoundingBox {
+        BoundingBox {
+            x_min: x,
+            y_min: 0.0,
+            x_max: x + 100.0,
+            y_max: 40.0,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_detection_and_end_to_end_metrics() {
+        let results = vec![
+            FrameResult {
+                detections: vec![detection(2.0, 0.9), detection(500.0, 0.6)],
+                labels: vec![label(0.0, "AB123"), label(200.0, "CD456")],
+                reads: vec![Some("AB123".into()), Some("XX000".into())],
+            },
+            FrameResult {
+                detections: vec![detection(0.0, 0.8)],
+                labels: vec![label(0.0, "EF789")],
+                reads: vec![Some("EF788".into())],
+            },
+        ];
+
+        let detection = detection_metrics(&results);
+        assert_eq!(detection.true_positives, 2);
+        assert_eq!(detection.false_positives, 1);
+        assert_eq!(detection.false_negatives, 1);
+        assert!((detection.precision - 2.0 / 3.0).abs() < 1e-9);
+        assert!((detection.recall - 2.0 / 3.0).abs() < 1e-9);
+        // Both true positives rank above the false positive
+        assert!((detection.map_50 - 2.0 / 3.0).abs() < 1e-9);
+
+        let e2e = end_to_end_metrics(&results);
+        assert_eq!(e2e.labeled_plates, 3);
+        assert_eq!(e2e.correct_reads, 1);
+    }
+
+    #[test]
+    fn test_parse_yolo_labels_with_plate_text() {
+        let plates = parse_yolo_labels("0 0.5 0.5 0.2 0.1 ab123\n0 0.1 0.1 0.1 0.1\n", 1000.0, 500.0);
+        assert_eq!(plates.len(), 2);
+        assert_eq!(plates[0].x_min, 400.0);
+        assert_eq!(plates[0].y_max, 275.0);
+        assert_eq!(plates[0].plate.as_deref(), Some("AB123"));
+        assert_eq!(plates[1].plate, None);
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let stats = LatencyStats::from_samples((1..=100).map(|v| v as f64).collect());
+        assert_eq!(stats.p50_ms, 50.0);
+        assert_eq!(stats.p99_ms, 99.0);
+        assert_eq!(stats.max_ms, 100.0);
+    }
+}
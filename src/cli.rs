@@ -0,0 +1,194 @@
+//! Command-line interface.
+
+use std::path::PathBuf;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use notification::AccessStatus;
+use crate::bench::AnnotationFormat;
+use crate::registry::AccessLevel;
+
+#[derive(Debug, Parser)]
+#[command(name = "yolo_plate_sentry", version, about = "Vehicle license plate detection and access control")]
+pub struct Cli {
+    /// Config file (JSON, TOML or YAML); defaults to config.{json,toml,yaml}
+    /// in the working directory
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Runs the service when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the gate service
+    Run,
+    /// Detect and read the plates in one image and print them as JSON
+    Detect {
+        image: PathBuf,
+        /// Write a copy of the image with the detections drawn on it
+        #[arg(long, value_name = "PATH")]
+        annotated: Option<PathBuf>,
+        /// Write every preprocessing stage of each plate's read into this directory
+        #[arg(long, value_name = "DIR")]
+        dump_preprocessing: Option<PathBuf>,
+    },
+    /// Detect and read the plates in every image of a directory, one JSON line per image
+    Batch {
+        dir: PathBuf,
+        /// Write annotated copies of the images into this directory
+        #[arg(long, value_name = "DIR")]
+        annotated: Option<PathBuf>,
+    },
+    /// Manage registered vehicles
+    #[command(subcommand)]
+    Whitelist(WhitelistCommand),
+    /// Inspect stored detection events
+    #[command(subcommand)]
+    Events(EventsCommand),
+    /// Measure OCR accuracy on a folder of labeled plate crops
+    Eval {
+        dataset_dir: PathBuf,
+        /// Ground truth as CSV or JSON
+        ground_truth: PathBuf,
+        #[arg(long, default_value = "ocr_eval_report.json")]
+        report: PathBuf,
+        #[arg(long, value_enum, default_value_t = Engine::Tesseract)]
+        engine: Engine,
+    },
+    /// Measure detection and end-to-end accuracy and latency on labeled frames
+    Bench {
+        frames_dir: PathBuf,
+        /// YOLO label directory or COCO JSON file
+        annotations: PathBuf,
+        #[arg(default_value = "yolo")]
+        format: AnnotationFormat,
+        #[arg(default_value = "bench_report.json")]
+        report: PathBuf,
+    },
+    /// Validate the configuration and the files it refers to, then exit
+    CheckConfig,
+    /// Read a password from stdin and print its hash for `api.auth.users`
+    HashPassword,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Engine {
+    Tesseract,
+    Segmentation,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WhitelistCommand {
+    /// Register a vehicle or update its registration
+    Add(VehicleArgs),
+    /// Remove a registered vehicle
+    Remove { plate: String },
+    /// Print registered vehicles as JSON lines
+    List,
+    /// Register every vehicle in a whitelist file, replacing existing entries
+    Import { path: PathBuf },
+    /// Write registered vehicles as a whitelist file, or to stdout
+    Export { path: Option<PathBuf> },
+}
+
+#[derive(Debug, Args)]
+pub struct VehicleArgs {
+    pub plate: String,
+    #[arg(long)]
+    pub owner: Option<String>,
+    /// Unit, apartment or department
+    #[arg(long)]
+    pub unit: Option<String>,
+    #[arg(long, value_parser = parse_access_level)]
+    pub level: Option<AccessLevel>,
+    /// First valid day, YYYY-MM-DD
+    #[arg(long)]
+    pub valid_from: Option<NaiveDate>,
+    /// Last valid day, YYYY-MM-DD
+    #[arg(long)]
+    pub valid_until: Option<NaiveDate>,
+    #[arg(long)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EventsCommand {
+    /// Print stored events matching the filters as JSON lines, newest first
+    Query(EventFilterArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct EventFilterArgs {
+    /// RFC 3339 time, inclusive
+    #[arg(long)]
+    pub from: Option<DateTime<Utc>>,
+    /// RFC 3339 time, exclusive
+    #[arg(long)]
+    pub to: Option<DateTime<Utc>>,
+    #[arg(long)]
+    pub plate: Option<String>,
+    /// Part of the plate number
+    #[arg(long)]
+    pub search: Option<String>,
+    /// allowed, denied or suspicious
+    #[arg(long, value_parser = parse_status)]
+    pub status: Option<AccessStatus>,
+    #[arg(long)]
+    pub camera: Option<String>,
+    #[arg(long)]
+    pub limit: Option<u32>,
+    #[arg(long)]
+    pub offset: Option<u32>,
+}
+
+fn parse_access_level(text: &str) -> Result<AccessLevel, String> {
+    crate::db::from_text(&text.to_ascii_lowercase()).map_err(|_| format!("unknown access level '{}'", text))
+}
+
+fn parse_status(text: &str) -> Result<AccessStatus, String> {
+    match text.to_ascii_lowercase().as_str() {
+        "allowed" => Ok(AccessStatus::Allowed),
+        "denied" => Ok(AccessStatus::Denied),
+        "suspicious" => Ok(AccessStatus::Suspicious),
+        other => Err(format!("unknown status '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::try_parse_from(["sentry", "--config", "site.toml", "whitelist", "add", "AB1234", "--level", "staff"])
+            .unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("site.toml")));
+        match cli.command {
+            Some(Command::Whitelist(WhitelistCommand::Add(vehicle))) => {
+                assert_eq!(vehicle.plate, "AB1234");
+                assert_eq!(vehicle.level, Some(AccessLevel::Staff));
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        let cli = Cli::try_parse_from(["sentry", "events", "query", "--status", "Denied", "--limit", "5"]).unwrap();
+        match cli.command {
+            Some(Command::Events(EventsCommand::Query(filter))) => {
+                assert_eq!(filter.status, Some(AccessStatus::Denied));
+                assert_eq!(filter.limit, Some(5));
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        assert!(Cli::try_parse_from(["sentry"]).unwrap().command.is_none());
+        assert!(Cli::try_parse_from(["sentry", "events", "query", "--status", "maybe"]).is_err());
+    }
+}
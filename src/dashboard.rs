@@ -0,0 +1,48 @@
+//! Guard dashboard: a single page built into the binary that uses the REST
+//! API and the live WebSocket, so there is no separate frontend to deploy.
+
+use actix_web::{get, web, HttpResponse};
+
+const INDEX_HTML: &str = include_str!("../web/index.html");
+const APP_JS: &str = include_str!("../web/app.js");
+const STYLE_CSS: &str = include_str!("../web/style.css");
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(index).service(app_js).service(style_css);
+}
+
+#[get("/")]
+async fn index() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(INDEX_HTML)
+}
+
+#[get("/assets/app.js")]
+async fn app_js() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/javascript; charset=utf-8").body(APP_JS)
+}
+
+#[get("/assets/style.css")]
+async fn style_css() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/css; charset=utf-8").body(STYLE_CSS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+
+    #[actix_web::test]
+    async fn test_serves_page_and_assets() {
+        let app = test::init_service(actix_web::App::new().configure(routes)).await;
+
+        let response = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/html; charset=utf-8");
+        let page = test::read_body(response).await;
+        assert!(std::str::from_utf8(&page).unwrap().contains("/assets/app.js"));
+
+        let request = test::TestRequest::get().uri("/assets/app.js").to_request();
+        let script = test::call_and_read_body(&app, request).await;
+        assert!(std::str::from_utf8(&script).unwrap().contains("/api/live/ws"));
+    }
+}
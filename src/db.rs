@@ -0,0 +1,49 @@
+//! SQLite connection shared by the event store and the vehicle registry.
+
+use std::str::FromStr;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use tracing::info;
+
+/// Opens (creating if needed) the database at `url` and runs pending migrations.
+pub async fn connect(url: &str) -> Result<SqlitePool, sqlx::Error> {
+    if let Some(dir) = url
+        .strip_prefix("sqlite://")
+        .and_then(|path| std::path::Path::new(path).parent())
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+
+    // Every connection to an in-memory database gets its own database,
+    // so keep exactly one alive
+    let pool = if url.contains(":memory:") {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?
+    } else {
+        SqlitePoolOptions::new().connect_with(options).await?
+    };
+
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    info!("Database ready at {}", url);
+
+    Ok(pool)
+}
+
+/// Stores a unit enum as its serde name, so the column matches the JSON API.
+pub fn to_text<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+pub fn from_text<T: DeserializeOwned>(text: &str) -> Result<T, sqlx::Error> {
+    serde_json::from_value(serde_json::Value::String(text.to_string()))
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
@@ -0,0 +1,273 @@
+//! Live stream of detections and list changes over Server-Sent Events and
+//! WebSocket, fed from the pipeline through a broadcast channel.
+
+use std::convert::Infallible;
+use std::time::Duration;
+use actix_web::web::{self, Bytes};
+use actix_web::{get, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::{BroadcastStream, IntervalStream};
+use tokio_stream::{Stream, StreamExt};
+use tracing::debug;
+use utoipa::{IntoParams, ToSchema};
+
+use notification::AccessStatus;
+use crate::api::{ApiError, ApiState};
+use crate::auth::{Caller, Role};
+use crate::store::EventRecord;
+
+/// Messages a slow client may fall behind by before it misses some.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Comment lines sent on idle SSE streams so proxies don't close them.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlateListKind {
+    Whitelist,
+    Watchlist,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ListChange {
+    Added,
+    Changed,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage {
+    /// A plate was read and decided on; `id` is 0 if storing it failed
+    Detection(Box<EventRecord>),
+    /// A plate was added to, changed on or removed from a list, which
+    /// changes the decision the next time it is read
+    ListChanged {
+        list: PlateListKind,
+        plate: String,
+        change: ListChange,
+    },
+    /// This client fell behind and missed messages; refetch to catch up
+    Lagged { skipped: u64 },
+}
+
+impl LiveMessage {
+    fn name(&self) -> &'static str {
+        match self {
+            LiveMessage::Detection(_) => "detection",
+            LiveMessage::ListChanged { .. } => "list_changed",
+            LiveMessage::Lagged { .. } => "lagged",
+        }
+    }
+}
+
+pub fn channel() -> broadcast::Sender<LiveMessage> {
+    broadcast::channel(CHANNEL_CAPACITY).0
+}
+
+/// Sends to whoever is listening; nobody listening is fine.
+pub fn publish(live: &broadcast::Sender<LiveMessage>, message: LiveMessage) {
+    let _ = live.send(message);
+}
+
+/// Which detections a client wants. List changes are always sent.
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LiveFilter {
+    pub camera_id: Option<String>,
+    #[param(inline)]
+    pub status: Option<AccessStatus>,
+}
+
+impl LiveFilter {
+    pub fn matches(&self, message: &LiveMessage) -> bool {
+        match message {
+            LiveMessage::Detection(record) => {
+                self.camera_id.as_ref().is_none_or(|camera_id| *camera_id == record.camera_id)
+                    && self.status.as_ref().is_none_or(|status| *status == record.event.access_status)
+            }
+            LiveMessage::ListChanged { .. } | LiveMessage::Lagged { .. } => true,
+        }
+    }
+}
+
+/// The messages of `receiver` that pass `filter`, with lag reported in band.
+fn messages(
+    receiver: broadcast::Receiver<LiveMessage>,
+    filter: LiveFilter,
+) -> impl Stream<Item = LiveMessage> {
+    BroadcastStream::new(receiver).filter_map(move |message| match message {
+        Ok(message) if filter.matches(&message) => Some(message),
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(LiveMessage::Lagged { skipped }),
+    })
+}
+
+fn sse_stream(
+    receiver: broadcast::Receiver<LiveMessage>,
+    filter: LiveFilter,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let events = messages(receiver, filter).map(|message| {
+        let data = serde_json::to_string(&message).unwrap_or_default();
+        Ok(Bytes::from(format!("event: {}\ndata: {}\n\n", message.name(), data)))
+    });
+    let keep_alive = IntervalStream::new(tokio::time::interval(KEEP_ALIVE))
+        .map(|_| Ok(Bytes::from_static(b": keep-alive\n\n")));
+    events.merge(keep_alive)
+}
+
+/// Server-Sent Events stream; the SSE event name is the message `type`.
+/// Browsers can't set headers on these, so pass the session token as `access_token`.
+#[utoipa::path(
+    tag = "live",
+    params(LiveFilter),
+    responses((status = 200, content_type = "text/event-stream", body = LiveMessage))
+)]
+#[get("/api/live/events")]
+pub async fn events_stream(
+    state: web::Data<ApiState>,
+    caller: Caller,
+    filter: web::Query<LiveFilter>,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(Role::Viewer)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(sse_stream(state.live.subscribe(), filter.into_inner())))
+}
+
+/// WebSocket stream; each text frame is one JSON message. Takes the session
+/// token as `access_token` too.
+#[utoipa::path(
+    tag = "live",
+    params(LiveFilter),
+    responses((status = 101, description = "Switching to WebSocket", body = LiveMessage))
+)]
+#[get("/api/live/ws")]
+pub async fn websocket(
+    state: web::Data<ApiState>,
+    caller: Caller,
+    filter: web::Query<LiveFilter>,
+    request: HttpRequest,
+    body: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    caller.require(Role::Viewer)?;
+    let (response, mut session, mut incoming) = actix_ws::handle(&request, body)?;
+    let mut messages = Box::pin(messages(state.live.subscribe(), filter.into_inner()));
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                message = messages.next() => {
+                    let Some(message) = message else { break };
+                    let text = serde_json::to_string(&message).unwrap_or_default();
+                    if session.text(text).await.is_err() {
+                        return;
+                    }
+                }
+                incoming = incoming.next() => match incoming {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        debug!("Live WebSocket client disconnected");
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use notification::{DetectionEvent, PlateType, ReadStatus};
+    use yolo_detector::BoundingBox;
+
+    fn detection(camera_id: &str, status: AccessStatus) -> LiveMessage {
+        LiveMessage::Detection(Box::new(EventRecord {
+            id: 7,
+            camera_id: camera_id.into(),
+            bbox: BoundingBox { x_min: 10.0, y_min: 20.0, x_max: 130.0, y_max: 60.0, confidence: 0.9 },
+            raw_text: "AB1234".into(),
+            processed_text: "AB1234".into(),
+            crop_path: None,
+            gate_results: Vec::new(),
+            event: DetectionEvent {
+                timestamp: Utc::now(),
+                plate_number: "AB1234".into(),
+                confidence: 0.8,
+                image_path: "detections/test.jpg".into(),
+                access_status: status,
+                read_status: ReadStatus::Valid,
+                plate_type: PlateType::Private,
+                char_confidences: Vec::new(),
+                reason: String::new(),
+                rule_id: None,
+                match_kind: None,
+                match_distance: None,
+                watchlisted: false,
+            },
+        }))
+    }
+
+    #[test]
+    fn test_filter_and_message_format() {
+        let filter = LiveFilter {
+            camera_id: Some("gate-1".into()),
+            status: Some(AccessStatus::Denied),
+        };
+        assert!(filter.matches(&detection("gate-1", AccessStatus::Denied)));
+        assert!(!filter.matches(&detection("gate-2", AccessStatus::Denied)));
+        assert!(!filter.matches(&detection("gate-1", AccessStatus::Allowed)));
+        let change = LiveMessage::ListChanged {
+            list: PlateListKind::Whitelist,
+            plate: "AB1234".into(),
+            change: ListChange::Removed,
+        };
+        assert!(filter.matches(&change));
+
+        let json = serde_json::to_value(detection("gate-1", AccessStatus::Denied)).unwrap();
+        assert_eq!(json["type"], "detection");
+        assert_eq!(json["plate_number"], "AB1234");
+        assert_eq!(json["id"], 7);
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["type"], "list_changed");
+        assert_eq!(json["change"], "removed");
+    }
+
+    #[tokio::test]
+    async fn test_sse_stream_filters_and_reports_lag() {
+        let live = channel();
+        let filter = LiveFilter {
+            status: Some(AccessStatus::Denied),
+            ..Default::default()
+        };
+        let mut stream = Box::pin(sse_stream(live.subscribe(), filter));
+        // The first keep-alive is sent straight away
+        assert_eq!(stream.next().await.unwrap().unwrap(), Bytes::from_static(b": keep-alive\n\n"));
+
+        publish(&live, detection("gate-1", AccessStatus::Allowed));
+        publish(&live, detection("gate-1", AccessStatus::Denied));
+        let chunk = stream.next().await.unwrap().unwrap();
+        let chunk = std::str::from_utf8(&chunk).unwrap();
+        assert!(chunk.starts_with("event: detection\ndata: {"));
+        assert!(chunk.contains("\"access_status\":\"Denied\""));
+
+        let mut lagging = Box::pin(messages(live.subscribe(), LiveFilter::default()));
+        for _ in 0..CHANNEL_CAPACITY + 3 {
+            publish(&live, detection("gate-1", AccessStatus::Allowed));
+        }
+        assert!(matches!(lagging.next().await, Some(LiveMessage::Lagged { skipped: 3 })));
+    }
+}
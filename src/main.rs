@@ -1,110 +1,320 @@
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{error, info, Level};
+use arc_swap::ArcSwap;
+use chrono::Timelike;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tracing::{debug, error, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
-use yolo_detector::LicensePlateDetector;
-use plate_ocr::PlateOcr;
-use notification::{NotificationService, DetectionEvent, AccessStatus};
+use yolo_detector::{LicensePlateDetector, QualityReport};
+use plate_ocr::{CharClassifier, PlateOcr, SuperResolution, TimeOfDay};
+use notification::{NotificationService, DetectionEvent};
 
-// Configuration structure
-#[derive(Debug, serde::Deserialize)]
-struct Config {
-    model_path: PathBuf,
-    camera_url: String,
-    line_token: Option<String>,
-    telegram_token: Option<String>,
-    telegram_chat_id: Option<String>,
-    whitelist_path: PathBuf,
+mod api;
+mod audit;
+mod auth;
+mod bench;
+mod cli;
+mod configuration;
+mod dashboard;
+mod db;
+mod gate;
+mod live;
+mod matching;
+mod pipeline;
+mod policy;
+mod registry;
+mod reload;
+mod status;
+mod store;
+mod watchlist;
+
+use clap::Parser;
+use audit::AuditLog;
+use auth::Caller;
+use cli::{Cli, Command, Engine, EventFilterArgs, EventsCommand, WhitelistCommand};
+use configuration::{Config, SecretUrl};
+use gate::Gates;
+use live::LiveMessage;
+use pipeline::{Checkpoint, PlateObservation, PlateSelector};
+use policy::Direction;
+use registry::{Vehicle, VehicleRegistry, WhitelistDiff};
+use reload::Settings;
+use status::{CameraMonitor, CameraState};
+use store::{EventQuery, EventRecord, EventStore};
+use watchlist::Watchlist;
+
+/// What `detect` and `batch` print for one image.
+#[derive(serde::Serialize)]
+struct ImageReport {
+    image: PathBuf,
+    plates: Vec<PlateReport>,
+}
+
+#[derive(serde::Serialize)]
+struct PlateReport {
+    bbox: yolo_detector::BoundingBox,
+    quality: QualityReport,
+    /// `None` when the crop failed the quality checks or OCR failed
+    reading: Option<plate_ocr::LicensePlateText>,
+    event: Option<DetectionEvent>,
+}
+
+/// OCR result for one plate and the decision made on it.
+struct PlateRead {
+    text: plate_ocr::LicensePlateText,
+    event: DetectionEvent,
 }
 
 struct App {
     detector: Arc<LicensePlateDetector>,
     ocr: Arc<PlateOcr>,
     notifier: Arc<NotificationService>,
-    whitelist: Arc<Mutex<std::collections::HashSet<String>>>,
+    registry: VehicleRegistry,
+    watchlist: Watchlist,
+    /// Policy, matching and quality settings, replaced on reload
+    settings: Arc<ArcSwap<Settings>>,
+    /// Serializes reloads from the file watcher, SIGHUP and the API
+    reload_lock: Mutex<()>,
+    /// Picks the plates to read, following vehicles across frames if tracking is on
+    selector: Mutex<PlateSelector>,
+    events: EventStore,
+    /// Who changed what through the API or the config file
+    audit: AuditLog,
+    /// Barriers opened for allowed plates
+    gates: Arc<Gates>,
+    camera_id: String,
+    lane: Option<String>,
+    direction: Option<Direction>,
+    camera: Arc<CameraMonitor>,
+    /// Detections and list changes for live API clients
+    live: broadcast::Sender<LiveMessage>,
 }
 
 impl App {
     async fn new(config: Config) -> Result<Self, Box<dyn Error>> {
+        let settings = Settings::from_config(&config)?;
+
         // Initialize YOLO detector
-        let detector = Arc::new(LicensePlateDetector::new(config.model_path).await?);
+        let detector = Arc::new(LicensePlateDetector::new(&config.model_path).await?);
         
         // Initialize OCR
-        let ocr = Arc::new(PlateOcr::new()?);
+        let ocr = Arc::new(build_ocr(&config)?);
         
         // Initialize notification service
         let notifier = Arc::new(NotificationService::new(
-            config.line_token,
-            config.telegram_token,
-            config.telegram_chat_id,
+            config.line_token.as_ref().map(|token| token.expose().to_string()),
+            config.telegram_token.as_ref().map(|token| token.expose().to_string()),
+            config.telegram_chat_id.clone(),
         ));
 
-        // Load whitelist
-        let whitelist = Arc::new(Mutex::new(load_whitelist(&config.whitelist_path)?));
+        // Open the event store and vehicle registry
+        let pool = db::connect(&config.database_url).await?;
+        let events = EventStore::new(pool.clone());
+        let registry = VehicleRegistry::new(pool.clone());
+        let watchlist = Watchlist::new(pool.clone());
+        let audit = AuditLog::new(pool);
+        let gates = Gates::from_config(&config.gates).map_err(|e| format!("Failed to set up gates: {}", e))?;
+
+        // Also run without a whitelist file, to revoke the plates of one no longer configured
+        let actor = Caller::local(reload::CONFIG_FILE_ACTOR);
+        reload::sync_whitelist(&registry, &audit, &actor, &settings.whitelist).await?;
+        info!("{} vehicles registered in total", registry.count().await?);
+        if let Some(path) = &config.watchlist_path {
+            import_watchlist(&watchlist, path).await?;
+        }
 
         Ok(Self {
             detector,
             ocr,
             notifier,
-            whitelist,
+            registry,
+            watchlist,
+            settings: Arc::new(ArcSwap::from_pointee(settings)),
+            reload_lock: Mutex::new(()),
+            selector: Mutex::new(PlateSelector::new(config.tracking)),
+            events,
+            audit,
+            gates: Arc::new(gates),
+            camera: Arc::new(CameraMonitor::new(config.camera_id.clone(), config.lane.clone(), config.direction)),
+            live: live::channel(),
+            camera_id: config.camera_id,
+            lane: config.lane,
+            direction: config.direction,
         })
     }
 
-    async fn process_frame(&self, frame: image::DynamicImage) -> Result<(), Box<dyn Error>> {
+    /// The lists, policy and gates plate reads from this camera are decided against.
+    fn checkpoint(&self) -> Checkpoint<'_> {
+        Checkpoint {
+            registry: &self.registry,
+            watchlist: &self.watchlist,
+            settings: self.settings.load_full(),
+            gates: &self.gates,
+            camera_id: &self.camera_id,
+            lane: self.lane.as_deref(),
+            direction: self.direction,
+        }
+    }
+
+    /// Detects plates in a frame and checks the quality of each crop.
+    async fn observe(&self, frame: image::DynamicImage) -> Result<Vec<PlateObservation>, Box<dyn Error>> {
         // Detect license plates in the frame
         let detections = self.detector.detect_license_plate(&frame).await?;
 
-        for bbox in detections {
-            // Extract the license plate region
-            let plate_image = frame.crop(
-                bbox.x_min as u32,
-                bbox.y_min as u32,
-                (bbox.x_max - bbox.x_min) as u32,
-                (bbox.y_max - bbox.y_min) as u32,
-            );
+        let settings = self.settings.load_full();
+        let mut observations = Vec::with_capacity(detections.len());
+        if !detections.is_empty() {
+            // Lighting is judged on the whole frame, once for all plates in it
+            let now = chrono::Local::now();
+            let time = TimeOfDay::new(now.hour(), now.minute()).expect("clock time is always in range");
+            let lighting = self.ocr.lighting(&frame, time);
+            let frame = Arc::new(frame);
 
-            // Perform OCR on the plate
-            let plate_text = self.ocr.process_plate(&plate_image)?;
+            for bbox in detections {
+                // Extract the license plate region
+                let crop = frame.crop_imm(
+                    bbox.x_min as u32,
+                    bbox.y_min as u32,
+                    (bbox.x_max - bbox.x_min) as u32,
+                    (bbox.y_max - bbox.y_min) as u32,
+                );
+                let quality = settings.quality.assess(&bbox, (frame.width(), frame.height()), &crop);
+                debug!("Plate crop quality {:.2}, issues {:?}", quality.score, quality.issues);
 
-            // Check if the plate is in the whitelist
-            let access_status = {
-                let whitelist = self.whitelist.lock().await;
-                if whitelist.contains(&plate_text.processed_text) {
-                    AccessStatus::Allowed
-                } else {
-                    AccessStatus::Suspicious
-                }
-            };
+                observations.push(PlateObservation {
+                    frame: Arc::clone(&frame),
+                    bbox,
+                    crop,
+                    lighting,
+                    quality,
+                });
+            }
+        }
+        Ok(observations)
+    }
 
-            // Create detection event
-            let event = DetectionEvent {
-                timestamp: chrono::Utc::now(),
-                plate_number: plate_text.processed_text,
-                confidence: plate_text.confidence,
-                image_path: save_detection_image(&frame, &bbox)?,
-                access_status,
-            };
+    async fn process_frame(&self, frame: image::DynamicImage) -> Result<(), Box<dyn Error>> {
+        let observations = self.observe(frame).await?;
+        self.camera.record_frame(observations.len());
 
-            // Send notification if suspicious
-            if matches!(event.access_status, AccessStatus::Suspicious) {
-                if let Err(e) = self.notifier.send_alert(&event).await {
-                    error!("Failed to send alert: {}", e);
-                }
+        let due = self.selector.lock().await.select(observations);
+        for observation in due {
+            self.process_plate(&observation).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a plate and decides on it, without saving or sending anything.
+    /// The event's `image_path` is left empty. `None` if OCR failed.
+    async fn read_plate(&self, observation: &PlateObservation) -> Result<Option<PlateRead>, Box<dyn Error>> {
+        // Perform OCR on the plate; an engine failure only skips this plate
+        let plate_text = match self.ocr.process_plate_with_lighting(&observation.crop, observation.lighting) {
+            Ok(plate_text) => plate_text,
+            Err(e) => {
+                error!("OCR failed for detection {:?}: {}", observation.bbox, e);
+                return Ok(None);
             }
+        };
+
+        let event = self.checkpoint().decide(&plate_text).await?;
+        Ok(Some(PlateRead { text: plate_text, event }))
+    }
+
+    async fn process_plate(&self, observation: &PlateObservation) -> Result<(), Box<dyn Error>> {
+        let Some(PlateRead { text: plate_text, mut event }) = self.read_plate(observation).await? else {
+            return Ok(());
+        };
+        // The barrier opens before anything slower happens
+        let gate_results = self.checkpoint().open_gates(&event).await;
+        event.image_path = save_detection_image(&observation.frame, &observation.bbox)?;
 
-            info!("Processed plate: {:?}", event);
+        if let Err(e) = self.notifier.notify(&event).await {
+            error!("Failed to send alert: {}", e);
         }
 
+        info!("Processed plate: {:?}", event);
+
+        // Persist the event; a database failure must not stop the camera loop
+        let mut record = EventRecord {
+            id: 0,
+            camera_id: self.camera_id.clone(),
+            bbox: observation.bbox.clone(),
+            raw_text: plate_text.text,
+            processed_text: plate_text.processed_text,
+            crop_path: save_plate_crop(&observation.crop)
+                .map_err(|e| error!("Failed to save plate crop: {}", e))
+                .ok(),
+            gate_results,
+            event,
+        };
+        match self.events.insert(&record).await {
+            Ok(id) => {
+                record.id = id;
+                self.camera.record_event(id, &record.event);
+            }
+            Err(e) => error!("Failed to store detection event: {}", e),
+        }
+        live::publish(&self.live, LiveMessage::Detection(Box::new(record)));
+
         Ok(())
     }
 
-    async fn run_camera_loop(&self, camera_url: String) -> Result<(), Box<dyn Error>> {
+    /// Detects, reads and decides on the plates in a still image without
+    /// saving, storing or sending anything. With `dump_dir`, the preprocessing
+    /// stages of each read are written there as `<image>_<plate>_<strategy>_*`.
+    async fn inspect_image(
+        &self,
+        path: &Path,
+        annotated: Option<&Path>,
+        dump_dir: Option<&Path>,
+    ) -> Result<ImageReport, Box<dyn Error>> {
+        let frame = image::open(path)?;
+        let original = annotated.map(|_| frame.clone());
+        let observations = self.observe(frame).await?;
+
+        let mut plates = Vec::with_capacity(observations.len());
+        for (i, observation) in observations.iter().enumerate() {
+            let read = if observation.quality.passes() {
+                self.read_plate(observation).await?
+            } else {
+                None
+            };
+            if let (Some(dir), Some(read)) = (dump_dir, &read) {
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+                let label = format!("{}_{}", stem, i);
+                self.ocr
+                    .dump_preprocessing(&observation.crop, observation.lighting, &read.text.strategy, dir, &label)?;
+            }
+            let (reading, event) = match read {
+                Some(PlateRead { text, event }) => (Some(text), Some(event)),
+                None => (None, None),
+            };
+            plates.push(PlateReport {
+                bbox: observation.bbox.clone(),
+                quality: observation.quality.clone(),
+                reading,
+                event,
+            });
+        }
+
+        if let (Some(out), Some(original)) = (annotated, original) {
+            let bboxes: Vec<_> = plates.iter().map(|plate| plate.bbox.clone()).collect();
+            annotate(&original, &bboxes).save(out)?;
+        }
+        Ok(ImageReport {
+            image: path.to_path_buf(),
+            plates,
+        })
+    }
+
+    async fn run_camera_loop(&self, camera_url: SecretUrl) -> Result<(), Box<dyn Error>> {
         // TODO: Implement camera capture loop using OpenCV or similar
         // For now, just log that we would process frames
-        info!("Would process camera feed from: {}", camera_url);
+        info!("Would process camera feed from: {}", camera_url.redacted());
+        self.camera.set_state(CameraState::Running);
         
         // Placeholder for camera loop
         loop {
@@ -113,10 +323,36 @@ impl App {
     }
 }
 
-fn load_whitelist(path: &PathBuf) -> Result<std::collections::HashSet<String>, Box<dyn Error>> {
-    let content = std::fs::read_to_string(path)?;
-    let plates: Vec<String> = serde_json::from_str(&content)?;
-    Ok(plates.into_iter().collect())
+fn build_ocr(config: &Config) -> Result<PlateOcr, Box<dyn Error>> {
+    let mut ocr = PlateOcr::new()?
+        .with_preprocessing(config.preprocessing.clone())
+        .with_retry(config.ocr_retry.clone())
+        .with_night_mode(config.night_mode.clone());
+    if let Some(classifier) = &config.char_classifier {
+        ocr = ocr.with_char_classifier(CharClassifier::from_config(classifier)?);
+    }
+    if let Some(super_resolution) = &config.super_resolution {
+        ocr = ocr.with_super_resolution(SuperResolution::from_config(super_resolution)?);
+    }
+    Ok(ocr)
+}
+
+/// Adds watchlist entries that are not on the watchlist yet.
+async fn import_watchlist(watchlist: &Watchlist, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut imported = 0;
+    for entry in watchlist::load_watchlist(path)? {
+        if watchlist.get(&entry.plate).await?.is_none() {
+            watchlist.upsert(&entry).await?;
+            imported += 1;
+        }
+    }
+    info!(
+        "Imported {} watchlist entries from {:?}, {} watched in total",
+        imported,
+        path,
+        watchlist.count().await?
+    );
+    Ok(())
 }
 
 fn save_detection_image(
@@ -130,41 +366,337 @@ fn save_detection_image(
     std::fs::create_dir_all("detections")?;
     
     // Draw bounding box and save image
-    let mut img_with_box = frame.clone();
-    imageproc::drawing::draw_hollow_rect_mut(
-        &mut img_with_box,
-        imageproc::rect::Rect::at(bbox.x_min as i32, bbox.y_min as i32)
-            .of_size(
-                (bbox.x_max - bbox.x_min) as u32,
-                (bbox.y_max - bbox.y_min) as u32,
-            ),
-        image::Rgba([255, 0, 0, 255]),
-    );
+    let img_with_box = annotate(frame, std::slice::from_ref(bbox));
     img_with_box.save(&path)?;
     
     Ok(path)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // Initialize logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .init();
+/// A copy of `frame` with a red box around each detection.
+fn annotate(frame: &image::DynamicImage, bboxes: &[yolo_detector::BoundingBox]) -> image::DynamicImage {
+    let mut annotated = frame.clone();
+    for bbox in bboxes {
+        imageproc::drawing::draw_hollow_rect_mut(
+            &mut annotated,
+            imageproc::rect::Rect::at(bbox.x_min as i32, bbox.y_min as i32)
+                .of_size(
+                    (bbox.x_max - bbox.x_min) as u32,
+                    (bbox.y_max - bbox.y_min) as u32,
+                ),
+            image::Rgba([255, 0, 0, 255]),
+        );
+    }
+    annotated
+}
+
+fn save_plate_crop(crop: &image::DynamicImage) -> Result<String, Box<dyn Error>> {
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S%.3f");
+    let path = format!("detections/{}_plate.jpg", timestamp);
+
+    std::fs::create_dir_all("detections")?;
+    crop.save(&path)?;
+
+    Ok(path)
+}
 
+/// Runs the gate service until the camera loop fails or the API server
+/// stops, reloading the whitelist and settings when their files change, on
+/// SIGHUP or on request through the API.
+async fn run_service(config: Config, config_path: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
     info!("Starting YoloPlateSentry...");
+    let camera_url = config.camera_url.clone();
+    let api_config = config.api.clone();
+    let app = App::new(config).await?;
 
-    // Load configuration
-    let config: Config = {
-        let config_text = std::fs::read_to_string("config.json")?;
-        serde_json::from_str(&config_text)?
+    let (reload_requests, reload_rx) = mpsc::channel(1);
+    let api = async {
+        match api_config {
+            Some(api_config) => {
+                if api_config.auth.is_none() {
+                    warn!("API authentication is off; anyone who can reach {} may change the lists", api_config.bind);
+                }
+                let state = api::ApiState {
+                    registry: app.registry.clone(),
+                    watchlist: app.watchlist.clone(),
+                    events: app.events.clone(),
+                    settings: Arc::clone(&app.settings),
+                    cameras: vec![Arc::clone(&app.camera)],
+                    reload: reload_requests,
+                    live: app.live.clone(),
+                    audit: app.audit.clone(),
+                    gates: Arc::clone(&app.gates),
+                };
+                api::serve(api_config, state).await
+            }
+            None => std::future::pending().await,
+        }
     };
 
-    // Initialize application
-    let app = App::new(config.clone()).await?;
+    tokio::select! {
+        result = app.run_camera_loop(camera_url) => result?,
+        result = api => result?,
+        _ = reload::watch_files(&app, config_path.as_deref()) => {}
+        _ = reload::reload_on_hangup(&app, config_path.as_deref()) => {}
+        _ = reload::reload_on_request(&app, config_path.as_deref(), reload_rx) => {}
+    }
+    Ok(())
+}
 
-    // Run the main camera loop
-    app.run_camera_loop(config.camera_url).await?;
+async fn run_detect(
+    config: Config,
+    image: &Path,
+    annotated: Option<&Path>,
+    dump_dir: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let app = App::new(config).await?;
+    let report = app.inspect_image(image, annotated, dump_dir).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+async fn run_batch(config: Config, dir: &Path, annotated_dir: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let app = App::new(config).await?;
+    if let Some(annotated_dir) = annotated_dir {
+        std::fs::create_dir_all(annotated_dir)?;
+    }
+
+    let images = bench::list_images(dir)?;
+    info!("Processing {} images from {:?}", images.len(), dir);
+    for image in images {
+        let annotated = annotated_dir.zip(image.file_name()).map(|(dir, name)| dir.join(name));
+        // One unreadable image must not end the batch
+        match app.inspect_image(&image, annotated.as_deref(), None).await {
+            Ok(report) => println!("{}", serde_json::to_string(&report)?),
+            Err(e) => error!("Failed to process {:?}: {}", image, e),
+        }
+    }
+    Ok(())
+}
+
+async fn run_whitelist(config: Config, command: WhitelistCommand) -> Result<(), Box<dyn Error>> {
+    let pool = db::connect(&config.database_url).await?;
+    let registry = VehicleRegistry::new(pool.clone());
+    let audit = AuditLog::new(pool);
+    let actor = Caller::local(format!("cli:{}", cli_user()));
+    match command {
+        WhitelistCommand::Add(args) => {
+            let before = registry.get(&args.plate).await?;
+            let mut vehicle = before.clone().unwrap_or_else(|| Vehicle::new(registry::normalize_plate(&args.plate)));
+            if let Some(owner) = args.owner {
+                vehicle.owner_name = owner;
+            }
+            if let Some(unit) = args.unit {
+                vehicle.unit = unit;
+            }
+            if let Some(level) = args.level {
+                vehicle.access_level = level;
+            }
+            if let Some(notes) = args.notes {
+                vehicle.notes = notes;
+            }
+            vehicle.valid_from = args.valid_from.or(vehicle.valid_from);
+            vehicle.valid_until = args.valid_until.or(vehicle.valid_until);
+            registry.upsert(&vehicle).await?;
+            let old = Vec::from_iter(before);
+            audit
+                .record_whitelist_diff(&actor, &old, &WhitelistDiff::between(&old, std::slice::from_ref(&vehicle)))
+                .await?;
+            println!("{}", serde_json::to_string(&vehicle)?);
+        }
+        WhitelistCommand::Remove { plate } => {
+            let old = Vec::from_iter(registry.get(&plate).await?);
+            if !registry.remove(&plate).await? {
+                return Err(format!("Plate {} is not registered", plate).into());
+            }
+            audit.record_whitelist_diff(&actor, &old, &WhitelistDiff::between(&old, &[])).await?;
+            info!("Removed {}", plate);
+        }
+        WhitelistCommand::List => {
+            for vehicle in registry.list().await? {
+                println!("{}", serde_json::to_string(&vehicle)?);
+            }
+        }
+        WhitelistCommand::Import { path } => {
+            let vehicles = registry::load_whitelist(&path)?;
+            registry::validate_whitelist(&vehicles)?;
+            // Plates missing from the file stay registered, so only compare the ones in it
+            let mut old = Vec::new();
+            for vehicle in &vehicles {
+                old.extend(registry.get(&vehicle.plate).await?);
+            }
+            registry.apply(&WhitelistDiff::between(&[], &vehicles)).await?;
+            audit.record_whitelist_diff(&actor, &old, &WhitelistDiff::between(&old, &vehicles)).await?;
+            info!(
+                "Imported {} vehicles from {:?}, {} registered in total",
+                vehicles.len(),
+                path,
+                registry.count().await?
+            );
+        }
+        WhitelistCommand::Export { path } => {
+            let json = serde_json::to_string_pretty(&registry.list().await?)?;
+            match path {
+                Some(path) => std::fs::write(path, json)?,
+                None => println!("{}", json),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Login name of whoever runs a command, for the audit log.
+fn cli_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Prints stored events matching the filters as JSON lines.
+async fn run_events_query(config: Config, filter: EventFilterArgs) -> Result<(), Box<dyn Error>> {
+    let filter = EventQuery {
+        from: filter.from,
+        to: filter.to,
+        plate: filter.plate,
+        search: filter.search,
+        status: filter.status,
+        camera_id: filter.camera,
+        limit: filter.limit,
+        offset: filter.offset,
+    };
+
+    let events = EventStore::new(db::connect(&config.database_url).await?);
+    for record in events.query(&filter).await? {
+        println!("{}", serde_json::to_string(&record)?);
+    }
+    Ok(())
+}
+
+async fn run_eval(
+    config: Config,
+    dataset_dir: &Path,
+    ground_truth: &Path,
+    report_path: &Path,
+    engine: Engine,
+) -> Result<(), Box<dyn Error>> {
+    let ocr = build_ocr(&config)?;
+    let labels = plate_ocr::eval::load_ground_truth(ground_truth)?;
+    info!("Loaded {} labels from {:?}", labels.len(), ground_truth);
+
+    let report = match engine {
+        Engine::Tesseract => plate_ocr::eval::evaluate("tesseract", dataset_dir, &labels, |image| {
+            ocr.process_plate(image)
+        }),
+        Engine::Segmentation => plate_ocr::eval::evaluate("segmentation", dataset_dir, &labels, |image| {
+            ocr.process_plate_segmented(image)
+        }),
+    };
+
+    report.write_json(report_path)?;
+    println!(
+        "{}: {} samples, accuracy {:.2}%, CER {:.4}, ECE {:.4} -> {}",
+        report.engine,
+        report.overall.samples,
+        report.overall.accuracy * 100.0,
+        report.overall.character_error_rate,
+        report.expected_calibration_error,
+        report_path.display()
+    );
+    Ok(())
+}
+
+async fn run_benchmark(
+    config: Config,
+    frames_dir: &Path,
+    annotations: &Path,
+    format: bench::AnnotationFormat,
+    report_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let frames = bench::load_annotations(format, frames_dir, annotations)?;
+    info!("Loaded {} labeled frames from {:?}", frames.len(), frames_dir);
+
+    let app = App::new(config).await?;
+    let report = bench::run(&app.detector, &app.ocr, &frames).await?;
+
+    std::fs::write(report_path, serde_json::to_string_pretty(&report)?)?;
+    info!("Benchmark report written to {:?}", report_path);
+    Ok(())
+}
+
+/// Checks the files the configuration refers to; the values themselves were
+/// validated when it was loaded.
+fn run_check_config(config: Config) -> Result<(), Box<dyn Error>> {
+    let mut problems = Vec::new();
+    let models = std::iter::once(("model_path", &config.model_path))
+        .chain(config.char_classifier.as_ref().map(|c| ("char_classifier.model_path", &c.model_path)))
+        .chain(config.super_resolution.as_ref().map(|c| ("super_resolution.model_path", &c.model_path)));
+    for (key, path) in models {
+        if !path.is_file() {
+            problems.push(format!("{}: {:?} not found", key, path));
+        }
+    }
+    if let Err(e) = Settings::from_config(&config) {
+        problems.push(format!("whitelist_path: {}", e));
+    }
+    if let Some(path) = &config.watchlist_path {
+        if let Err(e) = watchlist::load_watchlist(path) {
+            problems.push(format!("watchlist_path: {}", e));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(format!("Configuration problems:\n  - {}", problems.join("\n  - ")).into());
+    }
+    println!("Configuration OK\n{:#?}", config);
+    Ok(())
+}
 
+/// Reads one line so the password stays out of the shell history.
+fn run_hash_password() -> Result<(), Box<dyn Error>> {
+    eprintln!("Password:");
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err("Password must not be empty".into());
+    }
+    println!("{}", auth::hash_password(password).map_err(|e| e.to_string())?);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    // Needs no configuration, which may not exist yet
+    if let Some(Command::HashPassword) = cli.command {
+        return run_hash_password();
+    }
+
+    // Initialize logging; logs go to stderr so command output stays machine-readable
+    FmtSubscriber::builder()
+        .with_max_level(Level::INFO)
+        .with_writer(std::io::stderr)
+        .init();
+
+    // SENTRY_* environment variables override the config file
+    let config_path = cli.config.or_else(configuration::default_file);
+    let config = configuration::load(config_path.as_deref())?;
+    debug!("Configuration: {:?}", config);
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run_service(config, config_path).await,
+        Command::Detect { image, annotated, dump_preprocessing } => {
+            run_detect(config, &image, annotated.as_deref(), dump_preprocessing.as_deref()).await
+        }
+        Command::Batch { dir, annotated } => run_batch(config, &dir, annotated.as_deref()).await,
+        Command::Whitelist(command) => run_whitelist(config, command).await,
+        Command::Events(EventsCommand::Query(filter)) => run_events_query(config, filter).await,
+        Command::Eval { dataset_dir, ground_truth, report, engine } => {
+            run_eval(config, &dataset_dir, &ground_truth, &report, engine).await
+        }
+        Command::Bench { frames_dir, annotations, format, report } => {
+            run_benchmark(config, &frames_dir, &annotations, format, &report).await
+        }
+        Command::CheckConfig => run_check_config(config),
+        Command::HashPassword => unreachable!("handled before loading the configuration"),
+    }
+}
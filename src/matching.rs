@@ -0,0 +1,208 @@
+//! Fuzzy plate matching tolerant of typical OCR misreads.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use notification::MatchKind;
+use crate::registry::{AccessLevel, Vehicle};
+
+/// Characters read with less confidence still cost at least this fraction
+/// of a full edit, so an unreadable plate can't match everything.
+const MIN_CHAR_WEIGHT: f32 = 0.25;
+
+/// Substitution cost between two characters OCR commonly mixes up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Confusable {
+    pub a: char,
+    pub b: char,
+    pub cost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FuzzyConfig {
+    pub enabled: bool,
+    /// Symmetric substitution costs; any other substitution, insertion or
+    /// deletion costs 1.0
+    pub confusables: Vec<Confusable>,
+    /// Largest distance accepted per access level; levels not listed only
+    /// match exactly. Keep these below 1.0 so a confidently read wrong
+    /// character never matches
+    pub max_distance: HashMap<AccessLevel, f32>,
+}
+
+impl Default for FuzzyConfig {
+    fn default() -> Self {
+        let pairs = [
+            ('0', 'O', 0.2),
+            ('0', 'D', 0.3),
+            ('O', 'D', 0.3),
+            ('0', 'Q', 0.4),
+            ('1', 'I', 0.2),
+            ('1', 'L', 0.4),
+            ('1', '7', 0.5),
+            ('2', 'Z', 0.3),
+            ('5', 'S', 0.3),
+            ('6', 'G', 0.4),
+            ('8', 'B', 0.3),
+            ('3', '8', 0.5),
+            ('4', 'A', 0.5),
+        ];
+
+        Self {
+            enabled: true,
+            confusables: pairs
+                .into_iter()
+                .map(|(a, b, cost)| Confusable { a, b, cost })
+                .collect(),
+            max_distance: HashMap::from([
+                (AccessLevel::Guest, 0.3),
+                (AccessLevel::Staff, 0.5),
+                (AccessLevel::Vip, 0.5),
+            ]),
+        }
+    }
+}
+
+/// A registered vehicle matched to a read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlateMatch {
+    pub vehicle: Vehicle,
+    pub kind: MatchKind,
+    pub distance: f32,
+}
+
+impl FuzzyConfig {
+    fn substitution_cost(&self, read: char, registered: char) -> f32 {
+        if read == registered {
+            return 0.0;
+        }
+        self.confusables
+            .iter()
+            .find(|c| (c.a == read && c.b == registered) || (c.a == registered && c.b == read))
+            .map_or(1.0, |c| c.cost)
+    }
+
+    /// Weighted edit distance from a read to a registered plate. Edits of
+    /// read characters are scaled by their OCR confidence, so an uncertain
+    /// character is cheaper to correct than a confident one.
+    pub fn distance(&self, read: &str, confidences: &[f32], registered: &str) -> f32 {
+        let read: Vec<char> = read.chars().collect();
+        let registered: Vec<char> = registered.chars().collect();
+        let weight = |i: usize| {
+            if confidences.len() == read.len() {
+                confidences[i].clamp(MIN_CHAR_WEIGHT, 1.0)
+            } else {
+                1.0
+            }
+        };
+
+        let mut previous: Vec<f32> = (0..=registered.len()).map(|j| j as f32).collect();
+        for (i, &r) in read.iter().enumerate() {
+            let mut current = vec![previous[0] + weight(i); registered.len() + 1];
+            for (j, &g) in registered.iter().enumerate() {
+                let substitute = previous[j] + self.substitution_cost(r, g) * weight(i);
+                let delete = previous[j + 1] + weight(i);
+                let insert = current[j] + 1.0;
+                current[j + 1] = substitute.min(delete).min(insert);
+            }
+            previous = current;
+        }
+        previous[registered.len()]
+    }
+
+    /// The registered vehicle closest to `read` within its level's maximum
+    /// distance. Two vehicles at the same distance make the match ambiguous
+    /// and nothing is returned.
+    pub fn find_match(&self, read: &str, confidences: &[f32], vehicles: &[Vehicle]) -> Option<PlateMatch> {
+        if !self.enabled || read.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<(f32, &Vehicle)> = None;
+        let mut ambiguous = false;
+        for vehicle in vehicles {
+            let max = self.max_distance.get(&vehicle.access_level).copied().unwrap_or(0.0);
+            if max <= 0.0 {
+                continue;
+            }
+            let distance = self.distance(read, confidences, &vehicle.plate);
+            if distance > max {
+                continue;
+            }
+            match best {
+                Some((best_distance, _)) if (distance - best_distance).abs() < f32::EPSILON => ambiguous = true,
+                Some((best_distance, _)) if distance > best_distance => {}
+                _ => {
+                    best = Some((distance, vehicle));
+                    ambiguous = false;
+                }
+            }
+        }
+
+        match best {
+            Some((distance, vehicle)) if !ambiguous => Some(PlateMatch {
+                vehicle: vehicle.clone(),
+                kind: if distance == 0.0 { MatchKind::Exact } else { MatchKind::Fuzzy },
+                distance,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vehicle(plate: &str, level: AccessLevel) -> Vehicle {
+        Vehicle {
+            access_level: level,
+            ..Vehicle::new(plate)
+        }
+    }
+
+    #[test]
+    fn test_weighted_distance() {
+        let config = FuzzyConfig::default();
+        assert_eq!(config.distance("AB1234", &[], "AB1234"), 0.0);
+        assert!((config.distance("AB1294", &[], "AB1234") - 1.0).abs() < 1e-6);
+        assert!((config.distance("A81234", &[], "AB1234") - 0.3).abs() < 1e-6);
+        assert!((config.distance("AB123", &[], "AB1234") - 1.0).abs() < 1e-6);
+
+        // A confident misread costs more than an uncertain one
+        let uncertain = config.distance("AX1234", &[0.9, 0.3, 0.9, 0.9, 0.9, 0.9], "AB1234");
+        let confident = config.distance("AX1234", &[0.9, 0.95, 0.9, 0.9, 0.9, 0.9], "AB1234");
+        assert!(uncertain < confident);
+        assert!((uncertain - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_find_match_respects_level_limits_and_ambiguity() {
+        let config = FuzzyConfig::default();
+        let vehicles = vec![
+            vehicle("AB1234", AccessLevel::Staff),
+            vehicle("KK1555", AccessLevel::Guest),
+        ];
+
+        let staff = config.find_match("A81234", &[], &vehicles).unwrap();
+        assert_eq!(staff.vehicle.plate, "AB1234");
+        assert_eq!(staff.kind, MatchKind::Fuzzy);
+
+        // A confidently read wrong character never matches, an uncertain one may
+        assert!(config.find_match("AB1239", &[], &vehicles).is_none());
+        assert!(config.find_match("AB1239", &[0.9, 0.9, 0.9, 0.9, 0.9, 0.4], &vehicles).is_some());
+        assert!(config.find_match("KK1559", &[], &vehicles).is_none());
+
+        // A weaker confusion is within the staff limit but not the guest one
+        assert!(config.find_match("AB7234", &[], &vehicles).is_some());
+        assert!(config.find_match("KK7555", &[], &vehicles).is_none());
+        assert!(config.find_match("KK155S", &[], &vehicles).is_some());
+
+        let twins = vec![
+            vehicle("AB1234", AccessLevel::Staff),
+            vehicle("AB1235", AccessLevel::Staff),
+        ];
+        assert!(config.find_match("AB123X", &[], &twins).is_none());
+    }
+}
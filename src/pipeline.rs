@@ -0,0 +1,423 @@
+//! What happens to detected plates between the detector and the event
+//! store: which crops are read, what is decided on a read and which gates
+//! open for it. Nothing here needs the models.
+
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use notification::{AccessStatus, DetectionEvent, MatchKind, PlateType, ReadStatus};
+use plate_ocr::{LicensePlateText, Lighting, PlateValidity};
+use yolo_detector::{BoundingBox, PlateTracker, QualityReport, TrackerConfig};
+
+use crate::gate::{GateResult, Gates};
+use crate::policy::{Direction, PolicyInput};
+use crate::registry::{self, VehicleRegistry};
+use crate::reload::Settings;
+use crate::watchlist::Watchlist;
+
+/// A detected plate in one frame, kept until OCR runs on it.
+#[derive(Clone)]
+pub struct PlateObservation {
+    pub frame: Arc<image::DynamicImage>,
+    pub bbox: BoundingBox,
+    pub crop: image::DynamicImage,
+    pub lighting: Lighting,
+    pub quality: QualityReport,
+}
+
+/// Picks the plates to read from each frame.
+pub struct PlateSelector {
+    tracker: Option<PlateTracker<PlateObservation>>,
+}
+
+impl PlateSelector {
+    /// Without tracking every crop that passes the quality checks is read.
+    pub fn new(tracking: Option<TrackerConfig>) -> Self {
+        Self {
+            tracker: tracking.map(PlateTracker::new),
+        }
+    }
+
+    /// The observations to read now. With tracking, each vehicle is read
+    /// once, from its best crop so far, as soon as it is ready; vehicles that
+    /// never got ready are read when they leave.
+    pub fn select(&mut self, observations: Vec<PlateObservation>) -> Vec<PlateObservation> {
+        let Some(tracker) = &mut self.tracker else {
+            return observations
+                .into_iter()
+                .filter(|observation| {
+                    if !observation.quality.passes() {
+                        debug!("Skipping OCR for low quality crop: {:?}", observation.quality.issues);
+                    }
+                    observation.quality.passes()
+                })
+                .collect();
+        };
+
+        let update = tracker.update(
+            observations
+                .into_iter()
+                .map(|o| (o.bbox.clone(), o.quality.score, o.quality.passes(), o))
+                .collect(),
+        );
+        let mut due = Vec::new();
+        for track in update.ready {
+            debug!("Track {} ready after {} frames, best score {:.2}", track.id, track.frames, track.score);
+            due.push(track.best);
+        }
+        for track in update.finished {
+            if track.decided {
+                continue;
+            }
+            if !track.best.quality.passes() {
+                info!(
+                    "Skipping OCR for track {} ({} frames): best crop failed quality checks {:?}",
+                    track.id, track.frames, track.best.quality.issues
+                );
+                continue;
+            }
+            debug!("Track {} finished after {} frames, best score {:.2}", track.id, track.frames, track.score);
+            due.push(track.best);
+        }
+        due
+    }
+}
+
+/// The lists, access policy and gates of one camera that plate reads are
+/// decided against.
+pub struct Checkpoint<'a> {
+    pub registry: &'a VehicleRegistry,
+    pub watchlist: &'a Watchlist,
+    pub settings: Arc<Settings>,
+    pub gates: &'a Gates,
+    pub camera_id: &'a str,
+    pub lane: Option<&'a str>,
+    pub direction: Option<Direction>,
+}
+
+impl Checkpoint<'_> {
+    /// Looks a read up on both lists and decides on it. The event's
+    /// `image_path` is left empty.
+    pub async fn decide(&self, plate_text: &LicensePlateText) -> Result<DetectionEvent, sqlx::Error> {
+        let settings = &self.settings;
+        let candidates: Vec<String> = std::iter::once(&plate_text.processed_text)
+            .chain(plate_text.alternatives.iter().map(|c| &c.text))
+            .filter(|text| !text.is_empty())
+            .cloned()
+            .collect();
+
+        // Look the read and its alternatives up on both lists; the policy
+        // decides what a hit means
+        let watched = self.watchlist.find_first(&candidates).await?;
+        let mut registered = self.registry.find_first(&candidates).await?;
+        let mut match_kind = registered.as_ref().map(|vehicle| {
+            if vehicle.plate == registry::normalize_plate(&plate_text.processed_text) {
+                MatchKind::Exact
+            } else {
+                MatchKind::Alternative
+            }
+        });
+        // Only edits are measured; an alternative reading is the OCR's own guess
+        let mut match_distance = (match_kind == Some(MatchKind::Exact)).then_some(0.0);
+
+        // Without an exact hit, tolerate misreads of the primary reading
+        if registered.is_none() && settings.fuzzy_match.enabled {
+            let confidences: Vec<f32> = plate_text.chars.iter().map(|c| c.confidence).collect();
+            let vehicles = self.registry.list().await?;
+            if let Some(found) = settings.fuzzy_match.find_match(&plate_text.processed_text, &confidences, &vehicles) {
+                info!(
+                    "Plate {} fuzzily matched registered plate {} (distance {:.2})",
+                    plate_text.processed_text, found.vehicle.plate, found.distance
+                );
+                match_kind = Some(found.kind);
+                match_distance = Some(found.distance);
+                registered = Some(found.vehicle);
+            }
+        }
+
+        let plate_number = match (&watched, &registered) {
+            (Some(entry), _) => entry.plate.clone(),
+            (None, Some(vehicle)) => vehicle.plate.clone(),
+            (None, None) => plate_text.processed_text.clone(),
+        };
+        if plate_number != plate_text.processed_text && match_kind != Some(MatchKind::Fuzzy) {
+            info!(
+                "Plate {} matched via alternative reading {}",
+                plate_text.processed_text, plate_number
+            );
+        }
+
+        let decision = settings.policy.evaluate(&PolicyInput {
+            plate: &plate_text.processed_text,
+            listed_plate: (watched.is_some() || registered.is_some()).then_some(plate_number.as_str()),
+            watch: watched.as_ref(),
+            vehicle: registered.as_ref(),
+            match_kind,
+            camera_id: self.camera_id,
+            lane: self.lane,
+            direction: self.direction,
+            at: chrono::Local::now().naive_local(),
+            plate_type: plate_type(plate_text.plate_type),
+            confidence: plate_text.confidence,
+            read_valid: plate_text.is_valid(),
+        });
+        let mut reason = decision.reason;
+        if match_kind == Some(MatchKind::Fuzzy) {
+            reason.push_str(&format!(" (fuzzy match of {})", plate_text.processed_text));
+        } else if plate_number != plate_text.processed_text {
+            reason.push_str(&format!(" (via alternative reading of {})", plate_text.processed_text));
+        }
+
+        // Character confidences only line up with the primary reading
+        let char_confidences = if plate_number == plate_text.processed_text {
+            plate_text.chars.iter().map(|c| c.confidence).collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(DetectionEvent {
+            timestamp: chrono::Utc::now(),
+            plate_number,
+            confidence: plate_text.confidence,
+            image_path: String::new(),
+            access_status: decision.status,
+            read_status: read_status(plate_text.validity),
+            plate_type: plate_type(plate_text.plate_type),
+            char_confidences,
+            reason,
+            rule_id: decision.rule_id,
+            match_kind,
+            match_distance,
+            watchlisted: watched.is_some(),
+        })
+    }
+
+    /// Opens the gates for an allowed plate; other decisions open nothing.
+    pub async fn open_gates(&self, event: &DetectionEvent) -> Vec<GateResult> {
+        match event.access_status {
+            AccessStatus::Allowed => {
+                self.gates
+                    .open_for_plate(self.camera_id, self.lane, &event.plate_number)
+                    .await
+            }
+            AccessStatus::Denied | AccessStatus::Suspicious => Vec::new(),
+        }
+    }
+}
+
+fn read_status(validity: PlateValidity) -> ReadStatus {
+    match validity {
+        PlateValidity::Valid => ReadStatus::Valid,
+        PlateValidity::InvalidFormat => ReadStatus::InvalidFormat,
+        PlateValidity::Empty => ReadStatus::Empty,
+        PlateValidity::LowConfidence => ReadStatus::LowConfidence,
+    }
+}
+
+fn plate_type(plate_type: plate_ocr::PlateType) -> PlateType {
+    match plate_type {
+        plate_ocr::PlateType::Private => PlateType::Private,
+        plate_ocr::PlateType::Taxi => PlateType::Taxi,
+        plate_ocr::PlateType::Commercial => PlateType::Commercial,
+        plate_ocr::PlateType::Diplomat => PlateType::Diplomat,
+        plate_ocr::PlateType::Temporary => PlateType::Temporary,
+        plate_ocr::PlateType::Unknown => PlateType::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use async_trait::async_trait;
+    use plate_ocr::{PlateCandidate, PlateColor};
+
+    use crate::configuration::Config;
+    use crate::gate::{Gate, GateConfig, GateController, GateError, GateOutcome, GateRequest};
+    use crate::registry::Vehicle;
+    use crate::watchlist::WatchlistEntry;
+
+    /// Records the plates it was opened for instead of moving a barrier.
+    #[derive(Default)]
+    struct StubController {
+        opened: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl GateController for StubController {
+        async fn open(&self, request: &GateRequest) -> Result<(), GateError> {
+            self.opened.lock().unwrap().push(request.plate.clone().unwrap_or_default());
+            Ok(())
+        }
+    }
+
+    fn observation(x: u32) -> PlateObservation {
+        let frame = Arc::new(image::DynamicImage::new_rgb8(640, 480));
+        PlateObservation {
+            crop: frame.crop_imm(x, 300, 120, 30),
+            frame,
+            bbox: BoundingBox {
+                x_min: x as f32,
+                y_min: 300.0,
+                x_max: (x + 120) as f32,
+                y_max: 330.0,
+                confidence: 0.9,
+            },
+            lighting: Lighting::Day,
+            quality: QualityReport {
+                sharpness: 250.0,
+                brightness: 0.5,
+                clipped_fraction: 0.0,
+                width: 120,
+                height: 30,
+                issues: Vec::new(),
+                score: 0.8,
+            },
+        }
+    }
+
+    fn reading(plate: &str) -> LicensePlateText {
+        LicensePlateText {
+            text: plate.to_string(),
+            confidence: 0.95,
+            processed_text: plate.to_string(),
+            validity: PlateValidity::Valid,
+            chars: Vec::new(),
+            alternatives: Vec::new(),
+            strategy: plate_ocr::PRIMARY_STRATEGY.to_string(),
+            plate_type: plate_ocr::PlateType::Private,
+            background_color: PlateColor::Unknown,
+            text_color: PlateColor::Unknown,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tracked_vehicle_waiting_at_the_barrier_is_let_in() {
+        let pool = crate::db::connect("sqlite::memory:").await.unwrap();
+        let registry = VehicleRegistry::new(pool.clone());
+        registry.upsert(&Vehicle::new("AB1234")).await.unwrap();
+        let watchlist = Watchlist::new(pool);
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "model_path": "yolo.onnx",
+            "camera_url": "rtsp://gate-1",
+            "tracking": {}
+        }))
+        .unwrap();
+
+        let stub = Arc::new(StubController::default());
+        let barrier: GateConfig = serde_json::from_value(serde_json::json!({
+            "id": "barrier",
+            "pulse_ms": 0,
+            "backend": {"type": "command", "open": "true"}
+        }))
+        .unwrap();
+        let gates: Gates = std::iter::once(Gate::new(&barrier, stub.clone())).collect();
+        let checkpoint = Checkpoint {
+            registry: &registry,
+            watchlist: &watchlist,
+            settings: Arc::new(Settings::from_config(&config).unwrap()),
+            gates: &gates,
+            camera_id: "gate-1",
+            lane: None,
+            direction: None,
+        };
+        let mut selector = PlateSelector::new(config.tracking.clone());
+
+        // The car pulls up and waits at the closed barrier
+        let mut opened_in = Vec::new();
+        for (frame, x) in [200, 160, 158, 158, 158, 158, 158, 158].into_iter().enumerate() {
+            for _ in selector.select(vec![observation(x)]) {
+                let event = checkpoint.decide(&reading("AB1234")).await.unwrap();
+                assert_eq!(event.access_status, AccessStatus::Allowed, "{}", event.reason);
+                let results = checkpoint.open_gates(&event).await;
+                assert_eq!(results[0].outcome, GateOutcome::Opened);
+                opened_in.push(frame);
+            }
+        }
+        assert_eq!(opened_in, [2]);
+
+        // Once through, it is not read again as it drives off
+        for _ in 0..10 {
+            assert!(selector.select(Vec::new()).is_empty());
+        }
+        assert_eq!(*stub.opened.lock().unwrap(), ["AB1234"]);
+    }
+
+    #[tokio::test]
+    async fn test_only_watchlist_hits_are_flagged_for_priority_alerts() {
+        let pool = crate::db::connect("sqlite::memory:").await.unwrap();
+        let registry = VehicleRegistry::new(pool.clone());
+        let watchlist = Watchlist::new(pool);
+        watchlist.upsert(&WatchlistEntry::new("KK5555")).await.unwrap();
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "model_path": "yolo.onnx",
+            "camera_url": "rtsp://gate-1",
+            "policy": {"rules": [
+                {"id": "watchlist", "status": "Denied", "reason": "{watch}", "when": {"on_list": "watchlist"}},
+                {"id": "closed", "status": "Denied", "reason": "site closed"}
+            ]}
+        }))
+        .unwrap();
+        let gates: Gates = std::iter::empty().collect();
+        let checkpoint = Checkpoint {
+            registry: &registry,
+            watchlist: &watchlist,
+            settings: Arc::new(Settings::from_config(&config).unwrap()),
+            gates: &gates,
+            camera_id: "gate-1",
+            lane: None,
+            direction: None,
+        };
+
+        let watched = checkpoint.decide(&reading("KK5555")).await.unwrap();
+        assert_eq!(watched.access_status, AccessStatus::Denied);
+        assert!(watched.watchlisted);
+
+        // Denied by a site rule, which is no reason to page security
+        let closed = checkpoint.decide(&reading("AB1234")).await.unwrap();
+        assert_eq!(closed.access_status, AccessStatus::Denied);
+        assert!(!closed.watchlisted);
+    }
+
+    #[tokio::test]
+    async fn test_alternative_and_fuzzy_matches_are_recorded_as_such() {
+        let pool = crate::db::connect("sqlite::memory:").await.unwrap();
+        let registry = VehicleRegistry::new(pool.clone());
+        registry.upsert(&Vehicle::new("AB1234")).await.unwrap();
+        let watchlist = Watchlist::new(pool);
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "model_path": "yolo.onnx",
+            "camera_url": "rtsp://gate-1",
+            "fuzzy_match": {"max_distance": {"guest": 0.5}},
+            "policy": {"rules": [
+                {"id": "fuzzy", "status": "Suspicious", "reason": "{plate} read for {listed_plate}",
+                 "when": {"match_kind": "fuzzy"}},
+                {"id": "registered", "status": "Allowed", "reason": "registered", "when": {"on_list": "registered"}}
+            ]}
+        }))
+        .unwrap();
+        let gates: Gates = std::iter::empty().collect();
+        let checkpoint = Checkpoint {
+            registry: &registry,
+            watchlist: &watchlist,
+            settings: Arc::new(Settings::from_config(&config).unwrap()),
+            gates: &gates,
+            camera_id: "gate-1",
+            lane: None,
+            direction: None,
+        };
+
+        let exact = checkpoint.decide(&reading("AB1234")).await.unwrap();
+        assert_eq!((exact.match_kind, exact.match_distance), (Some(MatchKind::Exact), Some(0.0)));
+
+        let mut misread = reading("AB1Z34");
+        misread.alternatives = vec![PlateCandidate { text: "AB1234".into(), score: 0.6 }];
+        let alternative = checkpoint.decide(&misread).await.unwrap();
+        assert_eq!(alternative.plate_number, "AB1234");
+        assert_eq!((alternative.match_kind, alternative.match_distance), (Some(MatchKind::Alternative), None));
+
+        let fuzzy = checkpoint.decide(&reading("A81234")).await.unwrap();
+        assert_eq!(fuzzy.match_kind, Some(MatchKind::Fuzzy));
+        assert!(fuzzy.reason.starts_with("A81234 read for AB1234"), "{}", fuzzy.reason);
+    }
+}
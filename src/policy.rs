@@ -0,0 +1,374 @@
+//! Declarative access policy: an ordered list of rules, first match wins.
+
+use std::collections::HashSet;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use notification::{AccessStatus, MatchKind, PlateType};
+use crate::registry::{AccessLevel, TimeRange, Vehicle, VehicleAccess};
+use crate::watchlist::{WatchCategory, WatchlistEntry};
+
+/// Travel direction a camera watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Entry,
+    Exit,
+}
+
+/// Which list the plate was found on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlateList {
+    Watchlist,
+    Registered,
+    /// Neither registered nor watched
+    Unknown,
+}
+
+/// Conditions of a rule. Every field that is set must hold; empty lists and
+/// unset fields match anything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Conditions {
+    pub on_list: Option<PlateList>,
+    /// Exact plates this rule applies to, compared with the listed plate a
+    /// read matched, or with the read itself if it matched none
+    pub plates: Vec<String>,
+    pub watch_categories: Vec<WatchCategory>,
+    pub access_levels: Vec<AccessLevel>,
+    /// How the plate was matched to the registry
+    pub match_kind: Option<MatchKind>,
+    /// Whether the registered vehicle's dates, weekdays and hours allow it now
+    pub registration_valid: Option<bool>,
+    pub cameras: Vec<String>,
+    pub lanes: Vec<String>,
+    pub direction: Option<Direction>,
+    pub time_ranges: Vec<TimeRange>,
+    pub weekdays: Vec<Weekday>,
+    /// Whether today is in `PolicyConfig::holidays`
+    pub holiday: Option<bool>,
+    pub plate_types: Vec<PlateType>,
+    pub min_confidence: Option<f32>,
+    pub max_confidence: Option<f32>,
+    /// Whether the OCR read passed format and confidence validation
+    pub read_valid: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    #[serde(default)]
+    pub when: Conditions,
+    pub status: AccessStatus,
+    /// May use `{plate}` (as read), `{listed_plate}`, `{owner}`, `{level}`,
+    /// `{registration}` and `{watch}`
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    #[serde(default = "default_rules")]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            rules: default_rules(),
+            holidays: Vec::new(),
+        }
+    }
+}
+
+/// Watchlist first, then unreliable reads, then registered vehicles inside
+/// and outside their validity window, then everything else. Fuzzy matches
+/// count as registered; `FuzzyConfig::max_distance` decides which are close
+/// enough.
+fn default_rules() -> Vec<Rule> {
+    let rule = |id: &str, when: Conditions, status: AccessStatus, reason: &str| Rule {
+        id: id.to_string(),
+        when,
+        status,
+        reason: reason.to_string(),
+    };
+
+    vec![
+        rule(
+            "watchlist",
+            Conditions {
+                on_list: Some(PlateList::Watchlist),
+                ..Default::default()
+            },
+            AccessStatus::Denied,
+            "{watch}",
+        ),
+        rule(
+            "unreliable-read",
+            Conditions {
+                read_valid: Some(false),
+                ..Default::default()
+            },
+            AccessStatus::Suspicious,
+            "plate not read reliably",
+        ),
+        rule(
+            "registered",
+            Conditions {
+                on_list: Some(PlateList::Registered),
+                registration_valid: Some(true),
+                ..Default::default()
+            },
+            AccessStatus::Allowed,
+            "registered {level} vehicle",
+        ),
+        rule(
+            "registered-outside-window",
+            Conditions {
+                on_list: Some(PlateList::Registered),
+                ..Default::default()
+            },
+            AccessStatus::Suspicious,
+            "registered {level} vehicle: {registration}",
+        ),
+        rule("unregistered", Conditions::default(), AccessStatus::Suspicious, "not registered"),
+    ]
+}
+
+/// Everything known about one read when the decision is made.
+#[derive(Debug, Clone)]
+pub struct PolicyInput<'a> {
+    /// The plate as read
+    pub plate: &'a str,
+    /// The watchlist or registry plate the read matched, if any; it differs
+    /// from `plate` for fuzzy and alternative matches
+    pub listed_plate: Option<&'a str>,
+    pub watch: Option<&'a WatchlistEntry>,
+    pub vehicle: Option<&'a Vehicle>,
+    /// How `vehicle` was matched; `None` when unregistered
+    pub match_kind: Option<MatchKind>,
+    pub camera_id: &'a str,
+    pub lane: Option<&'a str>,
+    pub direction: Option<Direction>,
+    /// Local time of the read
+    pub at: NaiveDateTime,
+    pub plate_type: PlateType,
+    pub confidence: f32,
+    pub read_valid: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub status: AccessStatus,
+    /// Id of the matched rule, `None` if no rule matched
+    pub rule_id: Option<String>,
+    pub reason: String,
+}
+
+impl PolicyConfig {
+    /// Rejects duplicate or empty rule ids.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        for rule in &self.rules {
+            if rule.id.is_empty() {
+                return Err("Policy rule with empty id".into());
+            }
+            if !seen.insert(rule.id.as_str()) {
+                return Err(format!("Duplicate policy rule id '{}'", rule.id));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn evaluate(&self, input: &PolicyInput) -> Decision {
+        let registration = input.vehicle.map(|vehicle| vehicle.access_at(input.at));
+
+        match self.rules.iter().find(|rule| self.matches(&rule.when, input, registration)) {
+            Some(rule) => Decision {
+                status: rule.status.clone(),
+                rule_id: Some(rule.id.clone()),
+                reason: render_reason(&rule.reason, input, registration),
+            },
+            None => Decision {
+                status: AccessStatus::Suspicious,
+                rule_id: None,
+                reason: "no policy rule matched".into(),
+            },
+        }
+    }
+
+    fn matches(&self, when: &Conditions, input: &PolicyInput, registration: Option<VehicleAccess>) -> bool {
+        let list = if input.watch.is_some() {
+            PlateList::Watchlist
+        } else if input.vehicle.is_some() {
+            PlateList::Registered
+        } else {
+            PlateList::Unknown
+        };
+        let in_list = |values: &[String], value: Option<&str>| {
+            values.is_empty() || value.is_some_and(|v| values.iter().any(|x| x == v))
+        };
+
+        when.on_list.is_none_or(|on_list| on_list == list)
+            && in_list(&when.plates, Some(input.listed_plate.unwrap_or(input.plate)))
+            && (when.watch_categories.is_empty()
+                || input.watch.is_some_and(|w| when.watch_categories.contains(&w.category)))
+            && (when.access_levels.is_empty()
+                || input.vehicle.is_some_and(|v| when.access_levels.contains(&v.access_level)))
+            && when.match_kind.is_none_or(|kind| input.match_kind == Some(kind))
+            && when.registration_valid.is_none_or(|valid| {
+                registration.is_some() && (registration == Some(VehicleAccess::Permitted)) == valid
+            })
+            && in_list(&when.cameras, Some(input.camera_id))
+            && in_list(&when.lanes, input.lane)
+            && when.direction.is_none_or(|d| input.direction == Some(d))
+            && (when.time_ranges.is_empty() || when.time_ranges.iter().any(|r| r.contains(input.at.time())))
+            && (when.weekdays.is_empty() || when.weekdays.contains(&input.at.date().weekday()))
+            && when
+                .holiday
+                .is_none_or(|holiday| self.holidays.contains(&input.at.date()) == holiday)
+            && (when.plate_types.is_empty() || when.plate_types.contains(&input.plate_type))
+            && when.min_confidence.is_none_or(|min| input.confidence >= min)
+            && when.max_confidence.is_none_or(|max| input.confidence < max)
+            && when.read_valid.is_none_or(|valid| input.read_valid == valid)
+    }
+}
+
+fn render_reason(template: &str, input: &PolicyInput, registration: Option<VehicleAccess>) -> String {
+    let level = input
+        .vehicle
+        .map(|v| format!("{:?}", v.access_level).to_lowercase())
+        .unwrap_or_default();
+    let owner = input.vehicle.map(|v| v.owner_name.as_str()).unwrap_or_default();
+    let watch = input.watch.map(WatchlistEntry::describe).unwrap_or_default();
+    let registration = registration.map(|r| r.to_string()).unwrap_or_default();
+
+    template
+        .replace("{plate}", input.plate)
+        .replace("{listed_plate}", input.listed_plate.unwrap_or_default())
+        .replace("{owner}", owner)
+        .replace("{level}", &level)
+        .replace("{registration}", &registration)
+        .replace("{watch}", &watch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input<'a>(at: &str) -> PolicyInput<'a> {
+        PolicyInput {
+            plate: "AB1234",
+            listed_plate: None,
+            watch: None,
+            vehicle: None,
+            match_kind: None,
+            camera_id: "gate-1",
+            lane: Some("in-1"),
+            direction: Some(Direction::Entry),
+            at: at.parse().unwrap(),
+            plate_type: PlateType::Private,
+            confidence: 0.9,
+            read_valid: true,
+        }
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let policy = PolicyConfig::default();
+        policy.validate().unwrap();
+
+        let mut vehicle = Vehicle::new("AB1234");
+        vehicle.access_level = AccessLevel::Staff;
+        vehicle.weekdays = vec![Weekday::Mon];
+        let stolen = WatchlistEntry {
+            category: WatchCategory::Stolen,
+            ..WatchlistEntry::new("AB1234")
+        };
+
+        // 2026-10-19 is a Monday
+        let mut registered = input("2026-10-19T09:00:00");
+        registered.vehicle = Some(&vehicle);
+        registered.match_kind = Some(MatchKind::Exact);
+        let decision = policy.evaluate(&registered);
+        assert_eq!(decision.status, AccessStatus::Allowed);
+        assert_eq!(decision.rule_id.as_deref(), Some("registered"));
+        assert_eq!(decision.reason, "registered staff vehicle");
+
+        let mut sunday = input("2026-10-18T09:00:00");
+        sunday.vehicle = Some(&vehicle);
+        let decision = policy.evaluate(&sunday);
+        assert_eq!(decision.status, AccessStatus::Suspicious);
+        assert_eq!(decision.reason, "registered staff vehicle: not allowed on this day");
+
+        let mut watched = registered.clone();
+        watched.watch = Some(&stolen);
+        watched.read_valid = false;
+        let decision = policy.evaluate(&watched);
+        assert_eq!(decision.status, AccessStatus::Denied);
+        assert_eq!(decision.reason, "reported stolen");
+
+        let mut blurry = registered.clone();
+        blurry.read_valid = false;
+        assert_eq!(policy.evaluate(&blurry).rule_id.as_deref(), Some("unreliable-read"));
+
+        // Within the fuzzy limits a misread is as good as an exact read
+        let mut misread = registered.clone();
+        misread.plate = "A81234";
+        misread.match_kind = Some(MatchKind::Fuzzy);
+        assert_eq!(policy.evaluate(&misread).rule_id.as_deref(), Some("registered"));
+
+        assert_eq!(policy.evaluate(&input("2026-10-19T09:00:00")).rule_id.as_deref(), Some("unregistered"));
+    }
+
+    #[test]
+    fn test_custom_rules_from_config() {
+        let policy: PolicyConfig = serde_json::from_str(
+            r#"{
+                "holidays": ["2026-12-05"],
+                "rules": [
+                    {"id": "fuzzy-guest", "status": "Suspicious", "reason": "{plate} only resembles {listed_plate}",
+                     "when": {"match_kind": "fuzzy", "access_levels": ["guest"]}},
+                    {"id": "taxi-drop-off", "status": "Allowed", "reason": "taxi drop-off at {plate}",
+                     "when": {"plate_types": ["taxi"], "lanes": ["in-1"], "direction": "entry",
+                              "time_ranges": [{"start": "06:00", "end": "22:00"}], "min_confidence": 0.8}},
+                    {"id": "holiday-closed", "status": "Suspicious", "reason": "site closed",
+                     "when": {"holiday": true}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        policy.validate().unwrap();
+
+        let mut taxi = input("2026-10-19T21:30:00");
+        taxi.plate_type = PlateType::Taxi;
+        let decision = policy.evaluate(&taxi);
+        assert_eq!(decision.rule_id.as_deref(), Some("taxi-drop-off"));
+        assert_eq!(decision.reason, "taxi drop-off at AB1234");
+
+        taxi.direction = Some(Direction::Exit);
+        assert!(policy.evaluate(&taxi).rule_id.is_none());
+
+        let guest = Vehicle::new("AB1234");
+        let mut fuzzy = input("2026-10-19T10:00:00");
+        fuzzy.plate = "A81234";
+        fuzzy.listed_plate = Some("AB1234");
+        fuzzy.vehicle = Some(&guest);
+        fuzzy.match_kind = Some(MatchKind::Fuzzy);
+        let decision = policy.evaluate(&fuzzy);
+        assert_eq!(decision.rule_id.as_deref(), Some("fuzzy-guest"));
+        assert_eq!(decision.reason, "A81234 only resembles AB1234");
+        fuzzy.match_kind = Some(MatchKind::Exact);
+        assert!(policy.evaluate(&fuzzy).rule_id.is_none());
+
+        let holiday = input("2026-12-05T10:00:00");
+        assert_eq!(policy.evaluate(&holiday).rule_id.as_deref(), Some("holiday-closed"));
+
+        let mut duplicate = policy.clone();
+        duplicate.rules.push(duplicate.rules[0].clone());
+        assert!(duplicate.validate().is_err());
+    }
+}
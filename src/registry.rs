@@ -0,0 +1,516 @@
+//! Registered vehicles with access levels and validity windows.
+
+use std::fmt;
+use std::path::Path;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::{Executor, Row, Sqlite, Transaction};
+use utoipa::ToSchema;
+
+use crate::db::{from_text, to_text};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLevel {
+    #[default]
+    Guest,
+    Staff,
+    Vip,
+}
+
+/// Daily time window; `end` before `start` wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TimeRange {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeRange {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// A vehicle allowed through the gate, subject to its validity window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Vehicle {
+    pub plate: String,
+    #[serde(default)]
+    pub owner_name: String,
+    /// Unit, apartment or department
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub access_level: AccessLevel,
+    /// First day access is valid, inclusive
+    #[serde(default)]
+    pub valid_from: Option<NaiveDate>,
+    /// Last day access is valid, inclusive
+    #[serde(default)]
+    pub valid_until: Option<NaiveDate>,
+    /// Days access is allowed on; empty means every day
+    #[serde(default)]
+    #[schema(value_type = Vec<String>, example = json!(["Mon", "Tue"]))]
+    pub weekdays: Vec<Weekday>,
+    /// Times of day access is allowed in; empty means all day
+    #[serde(default)]
+    pub time_ranges: Vec<TimeRange>,
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// Outcome of checking a registered vehicle against its validity window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VehicleAccess {
+    Permitted,
+    NotYetValid,
+    Expired,
+    WrongDay,
+    OutsideHours,
+}
+
+impl fmt::Display for VehicleAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            VehicleAccess::Permitted => "permitted",
+            VehicleAccess::NotYetValid => "registration not yet valid",
+            VehicleAccess::Expired => "registration expired",
+            VehicleAccess::WrongDay => "not allowed on this day",
+            VehicleAccess::OutsideHours => "outside allowed hours",
+        };
+        f.write_str(text)
+    }
+}
+
+impl Vehicle {
+    /// A vehicle with no restrictions, as imported from a plain plate list.
+    pub fn new(plate: impl Into<String>) -> Self {
+        Self {
+            plate: plate.into(),
+            owner_name: String::new(),
+            unit: String::new(),
+            access_level: AccessLevel::default(),
+            valid_from: None,
+            valid_until: None,
+            weekdays: Vec::new(),
+            time_ranges: Vec::new(),
+            notes: String::new(),
+        }
+    }
+
+    /// Checks the validity dates, weekdays and time ranges at local time `at`.
+    pub fn access_at(&self, at: NaiveDateTime) -> VehicleAccess {
+        let date = at.date();
+        if self.valid_from.is_some_and(|from| date < from) {
+            VehicleAccess::NotYetValid
+        } else if self.valid_until.is_some_and(|until| date > until) {
+            VehicleAccess::Expired
+        } else if !self.weekdays.is_empty() && !self.weekdays.contains(&date.weekday()) {
+            VehicleAccess::WrongDay
+        } else if !self.time_ranges.is_empty() && !self.time_ranges.iter().any(|r| r.contains(at.time())) {
+            VehicleAccess::OutsideHours
+        } else {
+            VehicleAccess::Permitted
+        }
+    }
+}
+
+/// Reads a whitelist file: a JSON array of plate strings, of `Vehicle`
+/// objects, or a mix of both.
+pub fn load_whitelist(path: &Path) -> Result<Vec<Vehicle>, Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Plate(String),
+        Vehicle(Vehicle),
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let entries: Vec<Entry> = serde_json::from_str(&content)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Plate(plate) => Vehicle::new(normalize_plate(&plate)),
+            Entry::Vehicle(vehicle) => Vehicle { plate: normalize_plate(&vehicle.plate), ..vehicle },
+        })
+        .collect())
+}
+
+/// The form plates are registered and looked up in: uppercase like the OCR
+/// output, without whitespace or separators, so "ab-1234" is AB1234.
+pub fn normalize_plate(plate: &str) -> String {
+    plate
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '.' | '·'))
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Rejects whitelist entries with an empty or repeated plate.
+pub fn validate_whitelist(vehicles: &[Vehicle]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for vehicle in vehicles {
+        if vehicle.plate.trim().is_empty() {
+            return Err("Whitelist entry with empty plate".into());
+        }
+        if !seen.insert(vehicle.plate.as_str()) {
+            return Err(format!("Plate {} is listed more than once", vehicle.plate));
+        }
+    }
+    Ok(())
+}
+
+/// Changes between two versions of the whitelist.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WhitelistDiff {
+    pub added: Vec<Vehicle>,
+    /// Plates in both versions whose registration changed
+    pub changed: Vec<Vehicle>,
+    pub removed: Vec<String>,
+}
+
+impl WhitelistDiff {
+    pub fn between(old: &[Vehicle], new: &[Vehicle]) -> Self {
+        let mut diff = Self::default();
+        for vehicle in new {
+            match old.iter().find(|v| v.plate == vehicle.plate) {
+                None => diff.added.push(vehicle.clone()),
+                Some(previous) if previous != vehicle => diff.changed.push(vehicle.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|v| !new.iter().any(|n| n.plate == v.plate))
+            .map(|v| v.plate.clone())
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Repository for registered vehicles. Cheap to clone; clones share the pool.
+#[derive(Clone)]
+pub struct VehicleRegistry {
+    pool: SqlitePool,
+}
+
+impl VehicleRegistry {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds a vehicle or replaces the one with the same plate.
+    pub async fn upsert(&self, vehicle: &Vehicle) -> Result<(), sqlx::Error> {
+        write(&self.pool, vehicle, false, true).await
+    }
+
+    /// Adds a vehicle; `false` if its plate is already registered. Unlike
+    /// checking with `get` first, two requests for one plate can't both add it.
+    pub async fn insert(&self, vehicle: &Vehicle) -> Result<bool, sqlx::Error> {
+        match write(&self.pool, vehicle, false, false).await {
+            Ok(()) => Ok(true),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Applies a whitelist change in one transaction, so lookups see either
+    /// the old or the new whitelist and never a mix.
+    pub async fn apply(&self, diff: &WhitelistDiff) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        apply(&mut tx, diff, false).await?;
+        tx.commit().await
+    }
+
+    /// Makes the registry follow the whitelist file: rows from an earlier
+    /// version of the file that are gone from it are removed, and entries
+    /// that differ from their row are updated. Rows added through the API or
+    /// the command line are only touched if the file lists their plate.
+    /// Returns the rows the diff was computed from along with the diff.
+    pub async fn sync_whitelist_file(
+        &self,
+        vehicles: &[Vehicle],
+    ) -> Result<(Vec<Vehicle>, WhitelistDiff), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut old = Vec::new();
+        for row in sqlx::query("SELECT * FROM vehicles").fetch_all(&mut *tx).await? {
+            let vehicle = from_row(&row)?;
+            if row.try_get("from_whitelist_file")? || vehicles.iter().any(|v| v.plate == vehicle.plate) {
+                old.push(vehicle);
+            }
+        }
+
+        let diff = WhitelistDiff::between(&old, vehicles);
+        apply(&mut tx, &diff, true).await?;
+        for vehicle in vehicles {
+            sqlx::query("UPDATE vehicles SET from_whitelist_file = 1 WHERE plate = ?")
+                .bind(&vehicle.plate)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok((old, diff))
+    }
+
+    pub async fn get(&self, plate: &str) -> Result<Option<Vehicle>, sqlx::Error> {
+        sqlx::query("SELECT * FROM vehicles WHERE plate = ?")
+            .bind(normalize_plate(plate))
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| from_row(&row))
+            .transpose()
+    }
+
+    /// The first of `plates` that is registered, with its registration.
+    pub async fn find_first(&self, plates: &[String]) -> Result<Option<Vehicle>, sqlx::Error> {
+        for plate in plates {
+            if let Some(vehicle) = self.get(plate).await? {
+                return Ok(Some(vehicle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes a vehicle; `false` if it wasn't registered.
+    pub async fn remove(&self, plate: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM vehicles WHERE plate = ?")
+            .bind(normalize_plate(plate))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every registered vehicle, ordered by plate.
+    pub async fn list(&self) -> Result<Vec<Vehicle>, sqlx::Error> {
+        sqlx::query("SELECT * FROM vehicles ORDER BY plate")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query("SELECT COUNT(*) FROM vehicles")
+            .fetch_one(&self.pool)
+            .await?
+            .try_get(0)
+    }
+}
+
+async fn apply(tx: &mut Transaction<'_, Sqlite>, diff: &WhitelistDiff, from_file: bool) -> Result<(), sqlx::Error> {
+    for vehicle in diff.added.iter().chain(&diff.changed) {
+        write(&mut **tx, vehicle, from_file, true).await?;
+    }
+    for plate in &diff.removed {
+        sqlx::query("DELETE FROM vehicles WHERE plate = ?")
+            .bind(plate)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+const INSERT_VEHICLE: &str = "INSERT INTO vehicles (
+        plate, owner_name, unit, access_level, valid_from, valid_until,
+        weekdays, time_ranges, notes, from_whitelist_file
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+const REPLACE_VEHICLE: &str = "ON CONFLICT (plate) DO UPDATE SET
+        owner_name = excluded.owner_name,
+        unit = excluded.unit,
+        access_level = excluded.access_level,
+        valid_from = excluded.valid_from,
+        valid_until = excluded.valid_until,
+        weekdays = excluded.weekdays,
+        time_ranges = excluded.time_ranges,
+        notes = excluded.notes,
+        from_whitelist_file = excluded.from_whitelist_file";
+
+/// `from_file` marks the row as following the whitelist file. Without
+/// `replace` an existing row for the plate fails the unique constraint.
+async fn write<'e, E: Executor<'e, Database = Sqlite>>(
+    executor: E,
+    vehicle: &Vehicle,
+    from_file: bool,
+    replace: bool,
+) -> Result<(), sqlx::Error> {
+    let sql = if replace {
+        format!("{} {}", INSERT_VEHICLE, REPLACE_VEHICLE)
+    } else {
+        INSERT_VEHICLE.to_string()
+    };
+    sqlx::query(&sql)
+        .bind(&vehicle.plate)
+        .bind(&vehicle.owner_name)
+        .bind(&vehicle.unit)
+        .bind(to_text(&vehicle.access_level))
+        .bind(vehicle.valid_from.map(|d| d.to_string()))
+        .bind(vehicle.valid_until.map(|d| d.to_string()))
+        .bind(serde_json::json!(vehicle.weekdays).to_string())
+        .bind(serde_json::json!(vehicle.time_ranges).to_string())
+        .bind(&vehicle.notes)
+        .bind(from_file)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+fn from_row(row: &SqliteRow) -> Result<Vehicle, sqlx::Error> {
+    let date = |column: &str| -> Result<Option<NaiveDate>, sqlx::Error> {
+        row.try_get::<Option<String>, _>(column)?
+            .map(|text| text.parse().map_err(|e| sqlx::Error::Decode(Box::new(e))))
+            .transpose()
+    };
+
+    Ok(Vehicle {
+        plate: row.try_get("plate")?,
+        owner_name: row.try_get("owner_name")?,
+        unit: row.try_get("unit")?,
+        access_level: from_text(&row.try_get::<String, _>("access_level")?)?,
+        valid_from: date("valid_from")?,
+        valid_until: date("valid_until")?,
+        weekdays: json_column(row, "weekdays")?,
+        time_ranges: json_column(row, "time_ranges")?,
+        notes: row.try_get("notes")?,
+    })
+}
+
+fn json_column<T: DeserializeOwned>(row: &SqliteRow, column: &str) -> Result<T, sqlx::Error> {
+    serde_json::from_str(&row.try_get::<String, _>(column)?).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::new(date.parse().unwrap(), time.parse().unwrap())
+    }
+
+    #[test]
+    fn test_access_window() {
+        let vehicle: Vehicle = serde_json::from_str(
+            r#"{
+                "plate": "AB1234",
+                "access_level": "staff",
+                "valid_from": "2026-01-01",
+                "valid_until": "2026-12-31",
+                "weekdays": ["Mon", "Tue", "Wed", "Thu", "Fri"],
+                "time_ranges": [{"start": "07:00", "end": "19:00"}]
+            }"#,
+        )
+        .unwrap();
+
+        // 2026-10-19 is a Monday
+        assert_eq!(vehicle.access_at(at("2026-10-19", "08:30")), VehicleAccess::Permitted);
+        assert_eq!(vehicle.access_at(at("2026-10-19", "19:00")), VehicleAccess::OutsideHours);
+        assert_eq!(vehicle.access_at(at("2026-10-18", "08:30")), VehicleAccess::WrongDay);
+        assert_eq!(vehicle.access_at(at("2025-12-31", "08:30")), VehicleAccess::NotYetValid);
+        assert_eq!(vehicle.access_at(at("2027-01-04", "08:30")), VehicleAccess::Expired);
+
+        let night_shift = TimeRange {
+            start: "22:00".parse().unwrap(),
+            end: "06:00".parse().unwrap(),
+        };
+        assert!(night_shift.contains("23:30".parse().unwrap()));
+        assert!(night_shift.contains("05:59".parse().unwrap()));
+        assert!(!night_shift.contains("12:00".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_registry_round_trip() {
+        let registry = VehicleRegistry::new(crate::db::connect("sqlite::memory:").await.unwrap());
+        let mut vehicle = Vehicle::new("AB1234");
+        vehicle.owner_name = "Somchai".into();
+        vehicle.access_level = AccessLevel::Vip;
+        vehicle.weekdays = vec![Weekday::Sat, Weekday::Sun];
+        vehicle.valid_until = Some("2027-06-30".parse().unwrap());
+        assert!(registry.insert(&vehicle).await.unwrap());
+        assert!(!registry.insert(&Vehicle::new("AB1234")).await.unwrap());
+
+        assert_eq!(registry.get("AB1234").await.unwrap(), Some(vehicle.clone()));
+        assert_eq!(normalize_plate(" ab-12.34\n"), "AB1234");
+        assert_eq!(registry.get("ab 1234").await.unwrap(), Some(vehicle.clone()));
+        assert_eq!(registry.count().await.unwrap(), 1);
+
+        vehicle.access_level = AccessLevel::Staff;
+        registry.upsert(&vehicle).await.unwrap();
+        assert_eq!(registry.count().await.unwrap(), 1);
+        assert_eq!(registry.list().await.unwrap(), vec![vehicle.clone()]);
+
+        let candidates = vec!["XX0000".to_string(), "AB1234".to_string()];
+        let found = registry.find_first(&candidates).await.unwrap().unwrap();
+        assert_eq!(found.access_level, AccessLevel::Staff);
+
+        assert!(registry.remove("AB1234").await.unwrap());
+        assert!(!registry.remove("AB1234").await.unwrap());
+        assert_eq!(registry.count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_whitelist_diff() {
+        let registry = VehicleRegistry::new(crate::db::connect("sqlite::memory:").await.unwrap());
+        let old = vec![Vehicle::new("AB1234"), Vehicle::new("KK5555")];
+        registry.apply(&WhitelistDiff::between(&[], &old)).await.unwrap();
+
+        let mut visitor = Vehicle::new("ZZ9999");
+        visitor.valid_until = Some("2026-10-18".parse().unwrap());
+        let mut promoted = Vehicle::new("AB1234");
+        promoted.access_level = AccessLevel::Vip;
+        let new = vec![promoted.clone(), visitor.clone()];
+
+        let diff = WhitelistDiff::between(&old, &new);
+        assert_eq!(diff.added, vec![visitor]);
+        assert_eq!(diff.changed, vec![promoted.clone()]);
+        assert_eq!(diff.removed, vec!["KK5555".to_string()]);
+        assert!(WhitelistDiff::between(&new, &new).is_empty());
+
+        registry.apply(&diff).await.unwrap();
+        let plates: Vec<String> = registry.list().await.unwrap().into_iter().map(|v| v.plate).collect();
+        assert_eq!(plates, vec!["AB1234", "ZZ9999"]);
+        assert_eq!(registry.get("AB1234").await.unwrap(), Some(promoted));
+
+        assert!(validate_whitelist(&new).is_ok());
+        assert!(validate_whitelist(&[Vehicle::new("AB1234"), Vehicle::new("AB1234")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_whitelist_file_revokes_stale_rows() {
+        let registry = VehicleRegistry::new(crate::db::connect("sqlite::memory:").await.unwrap());
+        let mut contractor = Vehicle::new("AB1234");
+        contractor.access_level = AccessLevel::Staff;
+        let file = vec![contractor.clone(), Vehicle::new("KK5555")];
+        registry.sync_whitelist_file(&file).await.unwrap();
+        registry.upsert(&Vehicle::new("ZZ9999")).await.unwrap();
+
+        // While the service was stopped KK5555 was revoked and AB1234 demoted
+        contractor.access_level = AccessLevel::Guest;
+        let file = vec![contractor.clone(), Vehicle::new("QQ1111")];
+        let (old, diff) = registry.sync_whitelist_file(&file).await.unwrap();
+        assert_eq!(old.len(), 2);
+        assert_eq!(diff.added, vec![Vehicle::new("QQ1111")]);
+        assert_eq!(diff.changed, vec![contractor.clone()]);
+        assert_eq!(diff.removed, vec!["KK5555".to_string()]);
+
+        assert_eq!(registry.get("KK5555").await.unwrap(), None);
+        assert_eq!(registry.get("AB1234").await.unwrap(), Some(contractor));
+        // Registered through the API, so the file leaves it alone
+        assert!(registry.get("ZZ9999").await.unwrap().is_some());
+        assert!(registry.sync_whitelist_file(&file).await.unwrap().1.is_empty());
+
+        // A plate the file takes over follows it from then on
+        registry.sync_whitelist_file(&[Vehicle::new("ZZ9999")]).await.unwrap();
+        registry.sync_whitelist_file(&[]).await.unwrap();
+        assert_eq!(registry.count().await.unwrap(), 0);
+    }
+}
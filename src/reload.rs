@@ -0,0 +1,229 @@
+//! Hot reload of the whitelist and the settings that can change at runtime.
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info, warn};
+
+use yolo_detector::QualityConfig;
+use crate::audit::{AuditAction, AuditLog};
+use crate::auth::{Authenticator, Caller};
+use crate::configuration::{self, Config};
+use crate::live::{self, ListChange, LiveMessage, PlateListKind};
+use crate::matching::FuzzyConfig;
+use crate::policy::PolicyConfig;
+use crate::registry::{self, Vehicle, VehicleRegistry, WhitelistDiff};
+use crate::App;
+
+/// File changes are applied once they have been quiet this long, so an
+/// editor's write-and-rename counts as one change.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Actor recorded in the audit log for reloads nobody asked for through the
+/// API: file changes, SIGHUP and startup.
+pub const CONFIG_FILE_ACTOR: &str = "config-file";
+
+/// A reload asked for through the API by `caller`, answered with the outcome.
+pub struct ReloadRequest {
+    pub caller: Caller,
+    pub reply: oneshot::Sender<Result<(), String>>,
+}
+
+/// Settings swapped in as a whole on reload. Changes to anything else in the
+/// config file take effect after a restart.
+pub struct Settings {
+    pub policy: PolicyConfig,
+    pub fuzzy_match: FuzzyConfig,
+    pub quality: QualityConfig,
+    pub whitelist_path: Option<PathBuf>,
+    /// Whitelist file as last loaded
+    pub whitelist: Vec<Vehicle>,
+    /// API users and keys from `api.auth`; `None` lets every request through
+    /// as an admin
+    pub auth: Option<Authenticator>,
+    /// The whole config file as last loaded, for display
+    pub config: Config,
+}
+
+impl Settings {
+    /// Reads and validates the whitelist file of an already validated config.
+    pub fn from_config(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let whitelist = match &config.whitelist_path {
+            Some(path) => registry::load_whitelist(path)?,
+            None => Vec::new(),
+        };
+        registry::validate_whitelist(&whitelist)?;
+
+        Ok(Self {
+            policy: config.policy.clone(),
+            fuzzy_match: config.fuzzy_match.clone(),
+            quality: config.quality.clone(),
+            whitelist_path: config.whitelist_path.clone(),
+            whitelist,
+            auth: config.api.as_ref().and_then(|api| api.auth.as_ref()).map(Authenticator::new),
+            config: config.clone(),
+        })
+    }
+}
+
+/// Re-reads the config file and whitelist, applies the whitelist changes to
+/// the registry and swaps the new settings in. On any error the running
+/// settings stay as they were. The reload and every whitelist change it
+/// applied are audited as made by `actor`.
+pub async fn reload(app: &App, config_path: Option<&Path>, actor: &Caller) -> Result<(), Box<dyn Error>> {
+    let _reloading = app.reload_lock.lock().await;
+    let settings = Settings::from_config(&configuration::load(config_path)?)?;
+    if settings.auth.is_none() && app.settings.load().auth.is_some() {
+        return Err("api.auth can only be removed with a restart".into());
+    }
+
+    let diff = sync_whitelist(&app.registry, &app.audit, actor, &settings.whitelist).await?;
+    let changes = diff
+        .added
+        .iter()
+        .map(|v| (v.plate.as_str(), ListChange::Added))
+        .chain(diff.changed.iter().map(|v| (v.plate.as_str(), ListChange::Changed)))
+        .chain(diff.removed.iter().map(|plate| (plate.as_str(), ListChange::Removed)));
+    for (plate, change) in changes {
+        live::publish(
+            &app.live,
+            LiveMessage::ListChanged {
+                list: PlateListKind::Whitelist,
+                plate: plate.to_string(),
+                change,
+            },
+        );
+    }
+    // The changes are applied by now, so a failing audit log must not fail the reload
+    if let Err(e) = app.audit.record(actor, AuditAction::ConfigReloaded, "", None).await {
+        error!("Failed to audit the reload: {}", e);
+    }
+
+    app.settings.store(Arc::new(settings));
+    info!("Configuration reloaded");
+    Ok(())
+}
+
+/// Brings the registry in line with the whitelist file, both at startup and
+/// on reload, and audits each change as made by `actor`. The registry rather
+/// than the file as last loaded is the baseline, so edits made while the
+/// service was stopped are applied too.
+pub async fn sync_whitelist(
+    registry: &VehicleRegistry,
+    audit: &AuditLog,
+    actor: &Caller,
+    vehicles: &[Vehicle],
+) -> Result<WhitelistDiff, sqlx::Error> {
+    let (old, diff) = registry.sync_whitelist_file(vehicles).await?;
+    if diff.is_empty() {
+        info!("Whitelist unchanged");
+        return Ok(diff);
+    }
+
+    let plates = |vehicles: &[Vehicle]| vehicles.iter().map(|v| v.plate.as_str()).collect::<Vec<_>>().join(", ");
+    info!(
+        "Whitelist applied: added [{}], removed [{}], changed [{}]",
+        plates(&diff.added),
+        diff.removed.join(", "),
+        plates(&diff.changed)
+    );
+    // The changes are applied by now, so a failing audit log must not fail the caller
+    if let Err(e) = audit.record_whitelist_diff(actor, &old, &diff).await {
+        error!("Failed to audit the whitelist changes: {}", e);
+    }
+    Ok(diff)
+}
+
+/// Reloads whenever the config file or the whitelist changes on disk. Never
+/// returns; if the files can't be watched, reloading is left to SIGHUP.
+pub async fn watch_files(app: &App, config_path: Option<&Path>) {
+    if let Err(e) = watch(app, config_path).await {
+        warn!("Not watching configuration files: {}", e);
+    }
+    std::future::pending().await
+}
+
+async fn watch(app: &App, config_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    })?;
+
+    // Watch the directories, since editors often replace a file rather than write to it
+    let whitelist_path = app.settings.load().whitelist_path.clone();
+    let files = config_path
+        .into_iter()
+        .chain(whitelist_path.as_deref())
+        .map(absolute)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut dirs: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+    dirs.dedup();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    info!("Watching {:?} for changes", files);
+
+    while let Some(event) = rx.recv().await {
+        if event.kind.is_access() || !event.paths.iter().any(|path| files.contains(path)) {
+            continue;
+        }
+        tokio::time::sleep(DEBOUNCE).await;
+        while rx.try_recv().is_ok() {}
+
+        if let Err(e) = reload(app, config_path, &Caller::local(CONFIG_FILE_ACTOR)).await {
+            error!("Reload failed, keeping current configuration: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Reloads on SIGHUP. Never returns.
+#[cfg(unix)]
+pub async fn reload_on_hangup(app: &App, config_path: Option<&Path>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::hangup()) {
+        Ok(mut hangup) => {
+            while hangup.recv().await.is_some() {
+                info!("SIGHUP received, reloading configuration");
+                if let Err(e) = reload(app, config_path, &Caller::local(CONFIG_FILE_ACTOR)).await {
+                    error!("Reload failed, keeping current configuration: {}", e);
+                }
+            }
+        }
+        Err(e) => warn!("Not listening for SIGHUP: {}", e),
+    }
+    std::future::pending().await
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_hangup(_app: &App, _config_path: Option<&Path>) {
+    std::future::pending().await
+}
+
+/// Reloads on requests from the API. Never returns.
+pub async fn reload_on_request(app: &App, config_path: Option<&Path>, mut requests: mpsc::Receiver<ReloadRequest>) {
+    while let Some(ReloadRequest { caller, reply }) = requests.recv().await {
+        info!("Reload requested through the API by {}", caller.name);
+        let result = reload(app, config_path, &caller).await.map_err(|e| e.to_string());
+        if let Err(e) = &result {
+            error!("Reload failed, keeping current configuration: {}", e);
+        }
+        let _ = reply.send(result);
+    }
+    std::future::pending().await
+}
+
+/// `path` with its directory resolved, matching the paths in watcher events.
+fn absolute(path: &Path) -> std::io::Result<PathBuf> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Ok(dir.canonicalize()?.join(path.file_name().unwrap_or_default()))
+}
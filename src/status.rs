@@ -0,0 +1,92 @@
+//! Live status of each camera, as reported by the API.
+
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use notification::{AccessStatus, DetectionEvent};
+use crate::policy::Direction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraState {
+    /// Models loaded, stream not opened yet
+    Starting,
+    Running,
+}
+
+/// The most recent event stored for a camera.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LastEvent {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub plate_number: String,
+    pub access_status: AccessStatus,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CameraStatus {
+    pub camera_id: String,
+    pub lane: Option<String>,
+    pub direction: Option<Direction>,
+    pub state: CameraState,
+    pub started_at: DateTime<Utc>,
+    pub last_frame_at: Option<DateTime<Utc>>,
+    pub frames: u64,
+    /// Plates detected, including those skipped for poor quality
+    pub plates_detected: u64,
+    pub last_event: Option<LastEvent>,
+}
+
+/// Collects a camera's status from the pipeline. Shared with the API.
+pub struct CameraMonitor {
+    status: Mutex<CameraStatus>,
+}
+
+impl CameraMonitor {
+    pub fn new(camera_id: String, lane: Option<String>, direction: Option<Direction>) -> Self {
+        Self {
+            status: Mutex::new(CameraStatus {
+                camera_id,
+                lane,
+                direction,
+                state: CameraState::Starting,
+                started_at: Utc::now(),
+                last_frame_at: None,
+                frames: 0,
+                plates_detected: 0,
+                last_event: None,
+            }),
+        }
+    }
+
+    pub fn snapshot(&self) -> CameraStatus {
+        self.lock().clone()
+    }
+
+    pub fn set_state(&self, state: CameraState) {
+        self.lock().state = state;
+    }
+
+    pub fn record_frame(&self, plates: usize) {
+        let mut status = self.lock();
+        status.last_frame_at = Some(Utc::now());
+        status.frames += 1;
+        status.plates_detected += plates as u64;
+    }
+
+    pub fn record_event(&self, id: i64, event: &DetectionEvent) {
+        self.lock().last_event = Some(LastEvent {
+            id,
+            timestamp: event.timestamp,
+            plate_number: event.plate_number.clone(),
+            access_status: event.access_status.clone(),
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CameraStatus> {
+        // The status stays consistent even if a holder panicked
+        self.status.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,319 @@
+//! Persistent detection event store backed by SQLite.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::db::{from_text, to_text};
+use crate::gate::GateResult;
+use notification::{AccessStatus, DetectionEvent};
+use yolo_detector::BoundingBox;
+
+/// Default number of rows returned by `EventStore::query`.
+pub const DEFAULT_QUERY_LIMIT: u32 = 100;
+
+/// A detection event together with the context it was decided in.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EventRecord {
+    /// Assigned by the database; ignored on insert
+    #[serde(default)]
+    pub id: i64,
+    pub camera_id: String,
+    pub bbox: BoundingBox,
+    /// Text as returned by the OCR engine, before cleanup
+    pub raw_text: String,
+    pub processed_text: String,
+    /// Plate crop saved next to the annotated frame in `image_path`
+    pub crop_path: Option<String>,
+    /// What each gate serving the lane did; empty unless the plate was allowed
+    #[serde(default)]
+    pub gate_results: Vec<GateResult>,
+    #[serde(flatten)]
+    pub event: DetectionEvent,
+}
+
+/// Filter for `EventStore::query`; unset fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventQuery {
+    /// Inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub to: Option<DateTime<Utc>>,
+    pub plate: Option<String>,
+    /// Part of the plate number, for searching partial reads
+    pub search: Option<String>,
+    #[param(inline)]
+    pub status: Option<AccessStatus>,
+    pub camera_id: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Repository for detection events. Cheap to clone; clones share the pool.
+#[derive(Clone)]
+pub struct EventStore {
+    pool: SqlitePool,
+}
+
+impl EventStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a record and returns its id.
+    pub async fn insert(&self, record: &EventRecord) -> Result<i64, sqlx::Error> {
+        let event = &record.event;
+        let char_confidences = serde_json::json!(event.char_confidences).to_string();
+        let gate_results = serde_json::json!(record.gate_results).to_string();
+
+        let result = sqlx::query(
+            "INSERT INTO detection_events (
+                timestamp_ms, camera_id, plate_number, raw_text, processed_text, confidence,
+                bbox_x_min, bbox_y_min, bbox_x_max, bbox_y_max, bbox_confidence,
+                access_status, decision_reason, read_status, plate_type, char_confidences,
+                image_path, crop_path, rule_id, match_kind, match_distance, gate_results, watchlisted
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(event.timestamp.timestamp_millis())
+        .bind(&record.camera_id)
+        .bind(&event.plate_number)
+        .bind(&record.raw_text)
+        .bind(&record.processed_text)
+        .bind(event.confidence as f64)
+        .bind(record.bbox.x_min as f64)
+        .bind(record.bbox.y_min as f64)
+        .bind(record.bbox.x_max as f64)
+        .bind(record.bbox.y_max as f64)
+        .bind(record.bbox.confidence as f64)
+        .bind(to_text(&event.access_status))
+        .bind(&event.reason)
+        .bind(to_text(&event.read_status))
+        .bind(to_text(&event.plate_type))
+        .bind(char_confidences)
+        .bind(&event.image_path)
+        .bind(&record.crop_path)
+        .bind(&event.rule_id)
+        .bind(event.match_kind.as_ref().map(to_text))
+        .bind(event.match_distance.map(|d| d as f64))
+        .bind(gate_results)
+        .bind(event.watchlisted)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<EventRecord>, sqlx::Error> {
+        sqlx::query("SELECT * FROM detection_events WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| from_row(&row))
+            .transpose()
+    }
+
+    /// Events matching `filter`, newest first.
+    pub async fn query(&self, filter: &EventQuery) -> Result<Vec<EventRecord>, sqlx::Error> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM detection_events WHERE 1 = 1");
+        push_filters(&mut builder, filter);
+        builder
+            .push(" ORDER BY timestamp_ms DESC, id DESC LIMIT ")
+            .push_bind(filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT) as i64)
+            .push(" OFFSET ")
+            .push_bind(filter.offset.unwrap_or(0) as i64);
+
+        builder
+            .build()
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    /// Number of events matching `filter`, ignoring its limit and offset.
+    pub async fn count(&self, filter: &EventQuery) -> Result<i64, sqlx::Error> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM detection_events WHERE 1 = 1");
+        push_filters(&mut builder, filter);
+        builder.build().fetch_one(&self.pool).await?.try_get(0)
+    }
+}
+
+fn push_filters(builder: &mut QueryBuilder<'_, Sqlite>, filter: &EventQuery) {
+    if let Some(from) = filter.from {
+        builder.push(" AND timestamp_ms >= ").push_bind(from.timestamp_millis());
+    }
+    if let Some(to) = filter.to {
+        builder.push(" AND timestamp_ms < ").push_bind(to.timestamp_millis());
+    }
+    if let Some(plate) = &filter.plate {
+        builder.push(" AND plate_number = ").push_bind(plate.clone());
+    }
+    if let Some(search) = &filter.search {
+        builder.push(" AND instr(plate_number, ").push_bind(search.clone()).push(") > 0");
+    }
+    if let Some(status) = &filter.status {
+        builder.push(" AND access_status = ").push_bind(to_text(status));
+    }
+    if let Some(camera_id) = &filter.camera_id {
+        builder.push(" AND camera_id = ").push_bind(camera_id.clone());
+    }
+}
+
+fn from_row(row: &SqliteRow) -> Result<EventRecord, sqlx::Error> {
+    let timestamp_ms: i64 = row.try_get("timestamp_ms")?;
+    let timestamp = Utc
+        .timestamp_millis_opt(timestamp_ms)
+        .single()
+        .ok_or_else(|| sqlx::Error::Decode(format!("Invalid timestamp {}", timestamp_ms).into()))?;
+    let char_confidences: String = row.try_get("char_confidences")?;
+    let gate_results: String = row.try_get("gate_results")?;
+
+    Ok(EventRecord {
+        id: row.try_get("id")?,
+        camera_id: row.try_get("camera_id")?,
+        bbox: BoundingBox {
+            x_min: row.try_get::<f64, _>("bbox_x_min")? as f32,
+            y_min: row.try_get::<f64, _>("bbox_y_min")? as f32,
+            x_max: row.try_get::<f64, _>("bbox_x_max")? as f32,
+            y_max: row.try_get::<f64, _>("bbox_y_max")? as f32,
+            confidence: row.try_get::<f64, _>("bbox_confidence")? as f32,
+        },
+        raw_text: row.try_get("raw_text")?,
+        processed_text: row.try_get("processed_text")?,
+        crop_path: row.try_get("crop_path")?,
+        gate_results: serde_json::from_str(&gate_results).map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+        event: DetectionEvent {
+            timestamp,
+            plate_number: row.try_get("plate_number")?,
+            confidence: row.try_get::<f64, _>("confidence")? as f32,
+            image_path: row.try_get("image_path")?,
+            access_status: from_text(&row.try_get::<String, _>("access_status")?)?,
+            read_status: from_text(&row.try_get::<String, _>("read_status")?)?,
+            plate_type: from_text(&row.try_get::<String, _>("plate_type")?)?,
+            char_confidences: serde_json::from_str(&char_confidences)
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            reason: row.try_get("decision_reason")?,
+            rule_id: row.try_get("rule_id")?,
+            match_kind: row
+                .try_get::<Option<String>, _>("match_kind")?
+                .map(|text| from_text(&text))
+                .transpose()?,
+            match_distance: row.try_get::<Option<f64>, _>("match_distance")?.map(|d| d as f32),
+            watchlisted: row.try_get("watchlisted")?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use notification::{MatchKind, PlateType, ReadStatus};
+    use crate::gate::GateOutcome;
+
+    fn record(plate: &str, status: AccessStatus, minutes_ago: i64) -> EventRecord {
+        EventRecord {
+            id: 0,
+            camera_id: "gate-1".into(),
+            bbox: BoundingBox { x_min: 10.0, y_min: 20.0, x_max: 130.0, y_max: 60.0, confidence: 0.9 },
+            raw_text: format!("{} ", plate),
+            processed_text: plate.into(),
+            crop_path: None,
+            gate_results: vec![GateResult {
+                gate_id: "main".into(),
+                outcome: GateOutcome::Interlocked,
+                detail: Some("opened 2.0s ago".into()),
+            }],
+            event: DetectionEvent {
+                timestamp: Utc::now() - Duration::minutes(minutes_ago),
+                plate_number: plate.into(),
+                confidence: 0.8,
+                image_path: "detections/test.jpg".into(),
+                access_status: status,
+                read_status: ReadStatus::Valid,
+                plate_type: PlateType::Private,
+                char_confidences: vec![0.9, 0.7],
+                reason: "test".into(),
+                rule_id: Some("registered".into()),
+                match_kind: Some(MatchKind::Fuzzy),
+                match_distance: Some(0.3),
+                watchlisted: false,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_get_round_trip() {
+        let pool = crate::db::connect("sqlite::memory:").await.unwrap();
+        let store = EventStore::new(pool.clone());
+        let mut event = record("AB1234", AccessStatus::Allowed, 0);
+        event.event.read_status = ReadStatus::LowConfidence;
+        let id = store.insert(&event).await.unwrap();
+
+        let stored = store.query(&EventQuery::default()).await.unwrap().remove(0);
+        assert_eq!(stored.id, id);
+        assert_eq!(stored.event.plate_number, "AB1234");
+        assert_eq!(stored.raw_text, "AB1234 ");
+        assert_eq!(stored.event.char_confidences, vec![0.9, 0.7]);
+        assert_eq!(stored.event.plate_type, PlateType::Private);
+        assert_eq!(stored.event.access_status, AccessStatus::Allowed);
+        assert_eq!(stored.event.read_status, ReadStatus::LowConfidence);
+        let read_status: String = sqlx::query_scalar("SELECT read_status FROM detection_events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(read_status, "low_confidence");
+        assert_eq!(stored.event.rule_id.as_deref(), Some("registered"));
+        assert_eq!(stored.event.match_kind, Some(MatchKind::Fuzzy));
+        assert_eq!(stored.event.match_distance, Some(0.3));
+        assert_eq!(stored.gate_results[0].outcome, GateOutcome::Interlocked);
+
+        assert_eq!(store.get(id).await.unwrap().unwrap().event.plate_number, "AB1234");
+        assert!(store.get(id + 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_by_time_plate_and_status() {
+        let store = EventStore::new(crate::db::connect("sqlite::memory:").await.unwrap());
+        store.insert(&record("AB1234", AccessStatus::Allowed, 120)).await.unwrap();
+        store.insert(&record("AB1234", AccessStatus::Allowed, 5)).await.unwrap();
+        store.insert(&record("ZZ9999", AccessStatus::Suspicious, 1)).await.unwrap();
+
+        let recent = store
+            .query(&EventQuery {
+                from: Some(Utc::now() - Duration::minutes(30)),
+                to: Some(Utc::now()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].event.plate_number, "ZZ9999");
+
+        let by_plate = EventQuery {
+            plate: Some("AB1234".into()),
+            ..Default::default()
+        };
+        assert_eq!(store.query(&by_plate).await.unwrap().len(), 2);
+
+        let suspicious = EventQuery {
+            status: Some(AccessStatus::Suspicious),
+            ..Default::default()
+        };
+        let suspicious = store.query(&suspicious).await.unwrap();
+        assert_eq!(suspicious.len(), 1);
+        assert_eq!(suspicious[0].event.plate_number, "ZZ9999");
+
+        let search = EventQuery {
+            search: Some("B12".into()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(store.query(&search).await.unwrap().len(), 1);
+        assert_eq!(store.count(&search).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,189 @@
+//! Blacklisted and watched plates, such as stolen vehicles and banned visitors.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::Row;
+use utoipa::ToSchema;
+
+use crate::db::{from_text, to_text};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchCategory {
+    Stolen,
+    Banned,
+    #[default]
+    Other,
+}
+
+/// A plate that is denied entry and raises a priority alert when seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WatchlistEntry {
+    pub plate: String,
+    #[serde(default)]
+    pub category: WatchCategory,
+    /// Shown in the alert, e.g. a police report number
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl WatchlistEntry {
+    pub fn new(plate: impl Into<String>) -> Self {
+        Self {
+            plate: plate.into(),
+            category: WatchCategory::default(),
+            reason: String::new(),
+            notes: String::new(),
+        }
+    }
+
+    /// Human-readable reason for denying this plate.
+    pub fn describe(&self) -> String {
+        let category = match self.category {
+            WatchCategory::Stolen => "reported stolen",
+            WatchCategory::Banned => "banned",
+            WatchCategory::Other => "on watchlist",
+        };
+        if self.reason.is_empty() {
+            category.to_string()
+        } else {
+            format!("{}: {}", category, self.reason)
+        }
+    }
+}
+
+/// Reads a watchlist file: a JSON array of plate strings, of
+/// `WatchlistEntry` objects, or a mix of both.
+pub fn load_watchlist(path: &Path) -> Result<Vec<WatchlistEntry>, Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Plate(String),
+        Entry(WatchlistEntry),
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let entries: Vec<Entry> = serde_json::from_str(&content)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Plate(plate) => WatchlistEntry::new(plate),
+            Entry::Entry(entry) => entry,
+        })
+        .collect())
+}
+
+/// Repository for watched plates. Cheap to clone; clones share the pool.
+#[derive(Clone)]
+pub struct Watchlist {
+    pool: SqlitePool,
+}
+
+impl Watchlist {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds an entry or replaces the one with the same plate.
+    pub async fn upsert(&self, entry: &WatchlistEntry) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO watchlist (plate, category, reason, notes) VALUES (?, ?, ?, ?)
+            ON CONFLICT (plate) DO UPDATE SET
+                category = excluded.category,
+                reason = excluded.reason,
+                notes = excluded.notes",
+        )
+        .bind(&entry.plate)
+        .bind(to_text(&entry.category))
+        .bind(&entry.reason)
+        .bind(&entry.notes)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, plate: &str) -> Result<Option<WatchlistEntry>, sqlx::Error> {
+        sqlx::query("SELECT * FROM watchlist WHERE plate = ?")
+            .bind(plate)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| from_row(&row))
+            .transpose()
+    }
+
+    /// The first of `plates` that is on the watchlist.
+    pub async fn find_first(&self, plates: &[String]) -> Result<Option<WatchlistEntry>, sqlx::Error> {
+        for plate in plates {
+            if let Some(entry) = self.get(plate).await? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes an entry; `false` if the plate wasn't watched.
+    pub async fn remove(&self, plate: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM watchlist WHERE plate = ?")
+            .bind(plate)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Every watched plate, ordered by plate.
+    pub async fn list(&self) -> Result<Vec<WatchlistEntry>, sqlx::Error> {
+        sqlx::query("SELECT * FROM watchlist ORDER BY plate")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(from_row)
+            .collect()
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query("SELECT COUNT(*) FROM watchlist")
+            .fetch_one(&self.pool)
+            .await?
+            .try_get(0)
+    }
+}
+
+fn from_row(row: &SqliteRow) -> Result<WatchlistEntry, sqlx::Error> {
+    Ok(WatchlistEntry {
+        plate: row.try_get("plate")?,
+        category: from_text(&row.try_get::<String, _>("category")?)?,
+        reason: row.try_get("reason")?,
+        notes: row.try_get("notes")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_watchlist_lookup() {
+        let watchlist = Watchlist::new(crate::db::connect("sqlite::memory:").await.unwrap());
+        let entry = WatchlistEntry {
+            plate: "KK5555".into(),
+            category: WatchCategory::Stolen,
+            reason: "police report 2024/118".into(),
+            notes: String::new(),
+        };
+        watchlist.upsert(&entry).await.unwrap();
+
+        let candidates = vec!["AB1234".to_string(), "KK5555".to_string()];
+        let found = watchlist.find_first(&candidates).await.unwrap();
+        assert_eq!(found, Some(entry.clone()));
+        assert_eq!(found.unwrap().describe(), "reported stolen: police report 2024/118");
+        assert!(watchlist.find_first(&candidates[..1]).await.unwrap().is_none());
+
+        assert_eq!(watchlist.list().await.unwrap(), vec![entry]);
+        assert!(watchlist.remove("KK5555").await.unwrap());
+        assert!(!watchlist.remove("KK5555").await.unwrap());
+        assert_eq!(watchlist.count().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,370 @@
+// Guard dashboard. Talks only to the REST API and the live WebSocket.
+"use strict";
+
+const FEED_SIZE = 50;
+const PAGE_SIZE = 25;
+const CAMERA_REFRESH_MS = 5000;
+const RECONNECT_MS = 3000;
+const TOKEN_KEY = "sentry-token";
+
+const $ = (selector) => document.querySelector(selector);
+
+// Session token from the last login; unused when the API has no authentication
+let token = localStorage.getItem(TOKEN_KEY);
+
+// Images, links and WebSockets can't carry headers, so they get the token in the URL
+function withToken(url) {
+    if (!token) return url;
+    const separator = url.includes("?") ? "&" : "?";
+    return `${url}${separator}access_token=${encodeURIComponent(token)}`;
+}
+
+async function api(method, path, body) {
+    const options = { method, headers: {} };
+    if (token) {
+        options.headers["Authorization"] = `Bearer ${token}`;
+    }
+    if (body !== undefined) {
+        options.headers["Content-Type"] = "application/json";
+        options.body = JSON.stringify(body);
+    }
+    const response = await fetch(path, options);
+    if (response.status === 401) {
+        $("#login").hidden = false;
+    }
+    if (!response.ok) {
+        let message = `${response.status} ${response.statusText}`;
+        try {
+            message = (await response.json()).error || message;
+        } catch (_) {
+            // Not a JSON error body
+        }
+        const error = new Error(message);
+        error.status = response.status;
+        throw error;
+    }
+    return response.status === 204 ? null : response.json();
+}
+
+function toast(message, isError = false) {
+    const element = document.createElement("div");
+    element.className = isError ? "toast error" : "toast";
+    element.textContent = message;
+    $("#toasts").append(element);
+    setTimeout(() => element.remove(), 4000);
+}
+
+function formatTime(timestamp) {
+    return timestamp ? new Date(timestamp).toLocaleString() : "never";
+}
+
+function today() {
+    const now = new Date();
+    const offset = now.getTimezoneOffset() * 60000;
+    return new Date(now - offset).toISOString().slice(0, 10);
+}
+
+// Registering vehicles
+
+async function addToWhitelist(plate) {
+    try {
+        await api("POST", "/api/whitelist", { plate, access_level: "guest" });
+        toast(`${plate} added to the whitelist`);
+    } catch (e) {
+        toast(`Could not add ${plate}: ${e.message}`, true);
+    }
+}
+
+// A visitor may enter for the rest of today only. Never replaces an existing
+// registration: a registered plate is reported to the guard instead
+async function markAsVisitor(plate) {
+    const day = today();
+    const vehicle = { plate, access_level: "guest", valid_from: day, valid_until: day, notes: "Visitor" };
+    try {
+        await api("POST", "/api/whitelist", vehicle);
+        toast(`${plate} admitted as a visitor for today`);
+    } catch (e) {
+        if (e.status === 409) {
+            toast(`${plate} is already registered; change it in the whitelist instead`, true);
+        } else {
+            toast(`Could not admit ${plate}: ${e.message}`, true);
+        }
+    }
+}
+
+// Live feed
+
+function eventElement(event) {
+    const element = $("#event-template").content.firstElementChild.cloneNode(true);
+    element.classList.add(event.access_status);
+    const crop = element.querySelector(".crop");
+    if (event.crop_path) {
+        crop.src = withToken(`/api/events/${event.id}/crop`);
+        crop.alt = event.plate_number;
+    } else {
+        crop.style.visibility = "hidden";
+    }
+    element.querySelector(".plate").textContent = event.plate_number || "(unreadable)";
+    const status = element.querySelector(".status");
+    status.textContent = event.access_status;
+    status.classList.add(event.access_status);
+    element.querySelector(".reason").textContent = event.reason;
+    element.querySelector(".meta").textContent =
+        `${event.camera_id} · ${formatTime(event.timestamp)} · confidence ${(event.confidence * 100).toFixed(0)}%`;
+    const gates = element.querySelector(".gates");
+    gates.textContent = (event.gate_results || [])
+        .map((result) => `Gate ${result.gate_id} ${result.outcome}${result.detail ? ` (${result.detail})` : ""}`)
+        .join(" · ");
+    if ((event.gate_results || []).some((result) => result.outcome === "failed")) {
+        gates.classList.add("failed");
+    }
+
+    const actions = element.querySelector(".actions");
+    if (!event.plate_number) {
+        actions.remove();
+    } else {
+        element.querySelector(".whitelist").onclick = () => addToWhitelist(event.plate_number);
+        element.querySelector(".visitor").onclick = () => markAsVisitor(event.plate_number);
+    }
+    return element;
+}
+
+function showLive(event, fresh) {
+    const feed = $("#feed");
+    const element = eventElement(event);
+    if (fresh) {
+        element.classList.add("fresh");
+        feed.prepend(element);
+    } else {
+        feed.append(element);
+    }
+    while (feed.children.length > FEED_SIZE) {
+        feed.lastElementChild.remove();
+    }
+}
+
+async function loadRecent() {
+    const page = await api("GET", `/api/events?limit=${FEED_SIZE}`);
+    $("#feed").replaceChildren();
+    page.events.forEach((event) => showLive(event, false));
+}
+
+function connect() {
+    const scheme = location.protocol === "https:" ? "wss" : "ws";
+    const socket = new WebSocket(withToken(`${scheme}://${location.host}/api/live/ws`));
+    const connection = $("#connection");
+
+    socket.onopen = () => {
+        connection.textContent = "live";
+        connection.className = "connection online";
+        // Catch up on whatever happened while disconnected
+        loadRecent().catch((e) => toast(e.message, true));
+    };
+    socket.onmessage = (message) => {
+        const data = JSON.parse(message.data);
+        switch (data.type) {
+            case "detection":
+                showLive(data, true);
+                refreshCameras();
+                break;
+            case "list_changed":
+                toast(`${data.plate} ${data.change} on the ${data.list}`);
+                break;
+            case "lagged":
+                loadRecent().catch((e) => toast(e.message, true));
+                break;
+        }
+    };
+    socket.onclose = () => {
+        connection.textContent = "offline";
+        connection.className = "connection offline";
+        setTimeout(connect, RECONNECT_MS);
+    };
+}
+
+// Cameras
+
+async function openGate(cameraId) {
+    try {
+        const results = await api("POST", `/api/cameras/${encodeURIComponent(cameraId)}/open-gate`);
+        toast(`Opened ${results.map((result) => result.gate_id).join(", ")} at ${cameraId}`);
+    } catch (e) {
+        toast(`Could not open the gate at ${cameraId}: ${e.message}`, true);
+    }
+}
+
+function cameraElement(camera) {
+    const element = document.createElement("div");
+    element.className = "camera";
+    const title = document.createElement("h2");
+    title.textContent = camera.camera_id;
+    element.append(title);
+
+    const rows = [
+        ["State", camera.state],
+        ["Lane", [camera.lane, camera.direction].filter(Boolean).join(", ") || "-"],
+        ["Frames", `${camera.frames} (${camera.plates_detected} plates)`],
+        ["Last frame", formatTime(camera.last_frame_at)],
+        ["Last event", camera.last_event
+            ? `${camera.last_event.plate_number} ${camera.last_event.access_status}, ${formatTime(camera.last_event.timestamp)}`
+            : "none"],
+    ];
+    const list = document.createElement("dl");
+    for (const [name, value] of rows) {
+        const term = document.createElement("dt");
+        term.textContent = name;
+        const detail = document.createElement("dd");
+        detail.textContent = value;
+        list.append(term, detail);
+    }
+    element.append(list);
+
+    const button = document.createElement("button");
+    button.className = "open-gate";
+    button.textContent = "Open gate";
+    button.onclick = () => openGate(camera.camera_id);
+    element.append(button);
+    return element;
+}
+
+async function refreshCameras() {
+    try {
+        const cameras = await api("GET", "/api/cameras");
+        $("#cameras").replaceChildren(...cameras.map(cameraElement));
+    } catch (e) {
+        console.warn("Camera status unavailable", e);
+    }
+}
+
+// History
+
+let offset = 0;
+
+function searchQuery() {
+    const form = new FormData($("#search"));
+    const params = new URLSearchParams({ limit: PAGE_SIZE, offset });
+    for (const name of ["search", "status"]) {
+        const value = form.get(name).trim();
+        if (value) params.set(name, value);
+    }
+    for (const name of ["from", "to"]) {
+        const value = form.get(name);
+        if (value) params.set(name, new Date(value).toISOString());
+    }
+    return params;
+}
+
+function resultRow(event) {
+    const row = document.createElement("tr");
+    const cells = [
+        null,
+        event.plate_number || "(unreadable)",
+        event.access_status,
+        event.reason,
+        event.camera_id,
+        formatTime(event.timestamp),
+    ];
+    for (const value of cells) {
+        const cell = document.createElement("td");
+        if (value === null) {
+            if (event.crop_path) {
+                const crop = document.createElement("img");
+                crop.src = withToken(`/api/events/${event.id}/crop`);
+                crop.alt = event.plate_number;
+                cell.append(crop);
+            }
+        } else {
+            cell.textContent = value;
+        }
+        row.append(cell);
+    }
+    const link = document.createElement("a");
+    link.href = withToken(`/api/events/${event.id}/image`);
+    link.target = "_blank";
+    link.textContent = "Frame";
+    const cell = document.createElement("td");
+    cell.append(link);
+    row.append(cell);
+    return row;
+}
+
+async function search() {
+    try {
+        const page = await api("GET", `/api/events?${searchQuery()}`);
+        $("#results").replaceChildren(...page.events.map(resultRow));
+        const last = Math.min(page.offset + page.events.length, page.total);
+        $("#page").textContent = page.total ? `${page.offset + 1}–${last} of ${page.total}` : "No events";
+        $("#previous").disabled = page.offset === 0;
+        $("#next").disabled = last >= page.total;
+    } catch (e) {
+        toast(`Search failed: ${e.message}`, true);
+    }
+}
+
+$("#search").onsubmit = (e) => {
+    e.preventDefault();
+    offset = 0;
+    search();
+};
+$("#previous").onclick = () => {
+    offset = Math.max(0, offset - PAGE_SIZE);
+    search();
+};
+$("#next").onclick = () => {
+    offset += PAGE_SIZE;
+    search();
+};
+
+for (const tab of document.querySelectorAll(".tab")) {
+    tab.onclick = () => {
+        document.querySelectorAll(".tab, .panel").forEach((element) => element.classList.remove("active"));
+        tab.classList.add("active");
+        $(`#${tab.dataset.tab}`).classList.add("active");
+        if (tab.dataset.tab === "history") search();
+    };
+}
+
+// Session
+
+let started = false;
+
+async function start() {
+    let me;
+    try {
+        me = await api("GET", "/api/me");
+    } catch (_) {
+        return; // Not logged in; the login form is showing
+    }
+    $("#login").hidden = true;
+    $("#user").textContent = `${me.name} (${me.role})`;
+    $("#logout").hidden = !token;
+    document.body.dataset.role = me.role;
+    if (started) return;
+    started = true;
+    refreshCameras();
+    setInterval(refreshCameras, CAMERA_REFRESH_MS);
+    connect();
+}
+
+$("#login-form").onsubmit = async (e) => {
+    e.preventDefault();
+    const form = new FormData(e.target);
+    try {
+        const session = await api("POST", "/api/login", {
+            username: form.get("username"),
+            password: form.get("password"),
+        });
+        token = session.token;
+        localStorage.setItem(TOKEN_KEY, token);
+        e.target.reset();
+        start();
+    } catch (err) {
+        toast(`Login failed: ${err.message}`, true);
+    }
+};
+$("#logout").onclick = () => {
+    localStorage.removeItem(TOKEN_KEY);
+    location.reload();
+};
+
+start();